@@ -0,0 +1,80 @@
+use cql2::{Expr, Validator};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+
+/// Returns named cql2-text fixtures, from a single comparison up to
+/// thousands of clauses.
+fn fixtures() -> Vec<(&'static str, String)> {
+    let mut examples: Vec<_> = std::fs::read_dir("examples/text")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    examples.sort();
+    let examples: Vec<_> = examples
+        .iter()
+        .map(|path| format!("({})", std::fs::read_to_string(path).unwrap().trim()))
+        .collect();
+    let wide: Vec<_> = (0..2_000)
+        .map(|i| match i % 4 {
+            0 => format!("eo:cloud_cover < {i}"),
+            1 => format!("platform IN ('landsat-{i}', 'sentinel-{i}')"),
+            2 => format!("s_intersects(geometry, POINT({} {}))", i % 180, i % 90),
+            _ => format!("t_after(datetime, DATE('2020-01-{:02}'))", i % 28 + 1),
+        })
+        .collect();
+    vec![
+        ("small", "eo:cloud_cover < 10".to_string()),
+        ("examples", examples.join(" AND ")),
+        ("wide", wide.join(" AND ")),
+    ]
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, text) in fixtures() {
+        let json = text.parse::<Expr>().unwrap().to_json().unwrap();
+        group.bench_with_input(BenchmarkId::new("text", name), &text, |b, text| {
+            b.iter(|| cql2::parse_text(black_box(text)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("json", name), &json, |b, json| {
+            b.iter(|| cql2::parse_json(black_box(json)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn write(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write");
+    for (name, text) in fixtures() {
+        let expr: Expr = text.parse().unwrap();
+        group.bench_with_input(BenchmarkId::new("text", name), &expr, |b, expr| {
+            b.iter(|| black_box(expr).to_text().unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("json", name), &expr, |b, expr| {
+            b.iter(|| black_box(expr).to_json().unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("sql", name), &expr, |b, expr| {
+            b.iter(|| black_box(expr).to_sql().unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn validate(c: &mut Criterion) {
+    let validator = Validator::new().unwrap();
+    let mut group = c.benchmark_group("validate");
+    for (name, text) in fixtures() {
+        let expr: Expr = text.parse().unwrap();
+        let value = expr.to_value().unwrap();
+        group.bench_with_input(BenchmarkId::new("value", name), &value, |b, value| {
+            b.iter(|| validator.validate(black_box(value)).is_ok())
+        });
+        group.bench_with_input(BenchmarkId::new("expr", name), &expr, |b, expr| {
+            b.iter(|| validator.validate_expr(black_box(expr)).is_valid())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, write, validate);
+criterion_main!(benches);
@@ -1,12 +1,82 @@
 use anyhow::{anyhow, Result};
-use clap::{ArgAction, Parser, ValueEnum};
-use cql2::{Expr, Validator};
-use std::io::Read;
+#[cfg(feature = "generate")]
+use arbitrary::Unstructured;
+use boon::ValidationError;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "generate")]
+use cql2::ExprClass;
+#[cfg(feature = "search")]
+use cql2::FilterLang;
+use cql2::{Expr, Extent, Queryables, SourceMap, ToSqlOptions, Validator};
+use serde_json::{json, Value};
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    io::{BufRead, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 /// The CQL2 command-line interface.
+///
+/// If no subcommand is given, the arguments are those of `cql2 convert`.
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+
+    /// Verbosity.
+    ///
+    /// Provide this argument several times to turn up the chatter.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// How errors are written to standard error.
+    #[arg(long, default_value = "text", global = true)]
+    error_format: ErrorFormat,
+}
+
+/// A CLI subcommand.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Converts CQL2 between formats
+    Convert(ConvertArgs),
+
+    /// Validates CQL2, printing any warnings
+    Validate(InputArgs),
+
+    /// Converts CQL2 to a SQL query and parameters
+    Sql(SqlArgs),
+
+    /// Describes what CQL2 does
+    Explain(InputArgs),
+
+    /// Reads CQL2 interactively, one expression per line
+    Repl(ReplArgs),
+
+    /// Compares two CQL2 expressions
+    ///
+    /// Reports whether they are identical, equivalent after
+    /// canonicalization, or different, and prints the differing parts of
+    /// their canonical forms. Exits with status 1 if they are different.
+    Diff(DiffArgs),
+
+    /// Generates random, valid CQL2 expressions, one per line
+    #[cfg(feature = "generate")]
+    Generate(GenerateArgs),
+
+    /// Searches a STAC API with CQL2, writing the matching items as
+    /// newline-delimited JSON
+    #[cfg(feature = "search")]
+    Search(SearchArgs),
+}
+
+/// Arguments for reading and checking CQL2.
+#[derive(Debug, Args)]
+struct InputArgs {
     /// The input CQL2
     ///
     /// If not provided, or `-`, the CQL2 will be read from standard input. The
@@ -20,6 +90,21 @@ pub struct Cli {
     #[arg(short, long)]
     input_format: Option<InputFormat>,
 
+    /// A queryables JSON Schema to check the CQL2 against.
+    ///
+    /// The CQL2 is rejected if it uses properties that aren't queryables, or
+    /// if the types of its operands don't match the queryables.
+    /// `cql2 sql` also writes properties from their `x-column`, if any.
+    #[arg(long)]
+    queryables: Option<PathBuf>,
+}
+
+/// Arguments for `cql2 convert`.
+#[derive(Debug, Args)]
+struct ConvertArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
     /// The output format.
     ///
     /// If not provided, the format will be the same as the input.
@@ -30,11 +115,234 @@ pub struct Cli {
     #[arg(long, default_value_t = true, action = ArgAction::Set)]
     validate: bool,
 
-    /// Verbosity.
+    /// A directory of CQL2 files to convert, instead of a single input
     ///
-    /// Provide this argument several times to turn up the chatter.
-    #[arg(short, long, action = ArgAction::Count)]
-    verbose: u8,
+    /// Each file is converted into --out-dir, with the same name and an
+    /// extension for the output format.
+    #[arg(long, requires = "out_dir", conflicts_with = "input")]
+    in_dir: Option<PathBuf>,
+
+    /// The directory to write converted files to, with --in-dir
+    #[arg(long, requires = "in_dir")]
+    out_dir: Option<PathBuf>,
+}
+
+impl OutputFormat {
+    /// Writes an expression in this format.
+    fn render(&self, expr: &Expr) -> Result<String> {
+        Ok(match self {
+            OutputFormat::JsonPretty => expr.to_json_pretty()?,
+            OutputFormat::Json => expr.to_json()?,
+            OutputFormat::Text => expr.to_text()?,
+            OutputFormat::TextPretty => expr.to_text_pretty()?,
+            OutputFormat::Sql => serde_json::to_string_pretty(&expr.to_sql()?)?,
+            OutputFormat::Tree => expr.to_tree()?,
+            OutputFormat::Dot => expr.to_dot()?,
+        })
+    }
+
+    /// Returns the file extension for this format.
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::JsonPretty | OutputFormat::Json | OutputFormat::Sql => "json",
+            OutputFormat::Text | OutputFormat::TextPretty | OutputFormat::Tree => "txt",
+            OutputFormat::Dot => "dot",
+        }
+    }
+}
+
+/// Arguments for `cql2 sql`.
+#[derive(Debug, Args)]
+struct SqlArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Validate the CQL2
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    validate: bool,
+
+    /// How values are passed to the database.
+    #[arg(long, default_value = "bind")]
+    sql_params: SqlParams,
+
+    /// A JSON object mapping property names to SQL expressions.
+    ///
+    /// Properties that aren't in the mapping are written as quoted
+    /// identifiers, or, with --queryables, from their `x-column`.
+    #[arg(long)]
+    mapping: Option<PathBuf>,
+
+    /// A SQL expression for a JSONB column to read properties from, e.g.
+    /// `content`.
+    ///
+    /// Properties that aren't in the mapping are written as accessors into
+    /// this column, nested under --jsonb-prefix, instead of as quoted
+    /// identifiers.
+    #[arg(long)]
+    jsonb_column: Option<String>,
+
+    /// The keys that properties are nested under in --jsonb-column,
+    /// separated by commas.
+    #[arg(long, value_delimiter = ',', default_value = "properties")]
+    jsonb_prefix: Vec<String>,
+}
+
+/// How values are written in SQL output.
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum SqlParams {
+    /// As numbered parameters, printed as JSON with the query
+    Bind,
+
+    /// As SQL literals in the query, which is printed by itself
+    Inline,
+}
+
+/// Arguments for `cql2 repl`.
+#[derive(Debug, Args)]
+struct ReplArgs {
+    /// A queryables JSON Schema to check each expression against.
+    #[arg(long)]
+    queryables: Option<PathBuf>,
+}
+
+/// Arguments for `cql2 diff`.
+#[derive(Debug, Args)]
+struct DiffArgs {
+    /// The first CQL2 expression, as json or text
+    a: String,
+
+    /// The second CQL2 expression, as json or text
+    b: String,
+}
+
+/// Arguments for `cql2 generate`.
+#[cfg(feature = "generate")]
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    /// The number of expressions to generate
+    #[arg(short = 'n', long, default_value_t = 10)]
+    count: usize,
+
+    /// The kinds of predicates to use, comma-separated
+    ///
+    /// If not provided, all kinds are used.
+    #[arg(long, value_delimiter = ',')]
+    classes: Vec<GenerateClass>,
+
+    /// The output format
+    #[arg(short, long, default_value = "text")]
+    output_format: GenerateFormat,
+
+    /// A seed for the random number generator, for reproducible output
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+/// A kind of predicate for `cql2 generate`.
+#[cfg(feature = "generate")]
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum GenerateClass {
+    /// Numeric comparisons, BETWEEN, IN, and IS NULL
+    Comparison,
+
+    /// String comparisons, LIKE, and CASEI
+    Text,
+
+    /// Spatial operators
+    Spatial,
+
+    /// Temporal operators
+    Temporal,
+
+    /// Array operators
+    Array,
+}
+
+/// The output format for `cql2 generate`.
+#[cfg(feature = "generate")]
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum GenerateFormat {
+    /// cql2-text
+    Text,
+
+    /// cql2-json, compact
+    Json,
+}
+
+/// Arguments for `cql2 search`.
+#[cfg(feature = "search")]
+#[derive(Debug, Args)]
+struct SearchArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// The root URL of the STAC API
+    #[arg(long)]
+    stac_api: String,
+
+    /// The collections to search, comma-separated
+    #[arg(long, value_delimiter = ',')]
+    collections: Vec<String>,
+
+    /// The maximum number of items to write
+    #[arg(long)]
+    max_items: Option<usize>,
+}
+
+/// How errors are written.
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    /// Human-readable text
+    Text,
+
+    /// One JSON object per problem, with `code`, `message`, `pointer`, and
+    /// `span` fields
+    Json,
+}
+
+/// The kind of a CLI error, which determines its code and exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorCode {
+    /// Any other error, e.g. reading a file. Exits with 1.
+    Other,
+
+    /// The input couldn't be parsed. Exits with 3.
+    Parse,
+
+    /// The input isn't valid CQL2. Exits with 4.
+    Invalid,
+
+    /// The input uses properties that aren't queryables. Exits with 5.
+    UnknownProperty,
+
+    /// The input's operand types don't match the queryables. Exits with 5.
+    Type,
+}
+
+/// A CLI error with a stable code and, where possible, the locations of its
+/// problems.
+#[derive(Debug)]
+struct Diagnostic {
+    code: ErrorCode,
+
+    /// The human-readable error.
+    text: String,
+
+    /// The individual problems.
+    problems: Vec<Problem>,
+}
+
+/// A single problem in a [Diagnostic].
+#[derive(Debug)]
+struct Problem {
+    message: String,
+
+    /// A JSON pointer to the problem in the cql2-json representation of the
+    /// expression.
+    pointer: Option<String>,
+
+    /// The byte range of the problem in the input.
+    span: Option<Range<usize>>,
 }
 
 /// The input CQL2 format.
@@ -59,8 +367,32 @@ enum OutputFormat {
     /// cql2-text
     Text,
 
+    /// cql2-text, pretty-printed
+    TextPretty,
+
     /// SQL
     Sql,
+
+    /// An indented tree of operations and values
+    Tree,
+
+    /// A Graphviz DOT graph of operations and values
+    Dot,
+}
+
+/// Parsed CQL2 input.
+struct Input {
+    /// The input, as it was provided.
+    source: String,
+
+    /// The format of the input.
+    format: InputFormat,
+
+    /// The parsed expression.
+    expr: Expr,
+
+    /// The locations of the expression's parts, for cql2-text input.
+    source_map: Option<SourceMap>,
 }
 
 impl Cli {
@@ -74,68 +406,705 @@ impl Cli {
     ///
     /// let cli = Cli::try_parse_from(&["cql2", "landsat:scene_id = 'LC82030282019133LGN00'"]).unwrap();
     /// cli.run();
+    ///
+    /// let cli = Cli::try_parse_from(&["cql2", "sql", "landsat:scene_id = 'LC82030282019133LGN00'"]).unwrap();
+    /// cli.run();
     /// ```
     pub fn run(self) {
+        let error_format = self.error_format;
         if let Err(err) = self.run_inner() {
-            eprintln!("{}", err);
-            std::process::exit(1)
+            let diagnostic = err.downcast_ref::<Diagnostic>();
+            match error_format {
+                ErrorFormat::Text => eprintln!("{}", err),
+                ErrorFormat::Json => match diagnostic {
+                    Some(diagnostic) => {
+                        for problem in &diagnostic.problems {
+                            eprintln!("{}", problem.to_json(diagnostic.code));
+                        }
+                    }
+                    None => eprintln!(
+                        "{}",
+                        json!({ "code": ErrorCode::Other.as_str(), "message": err.to_string(), "pointer": null, "span": null })
+                    ),
+                },
+            }
+            std::process::exit(diagnostic.map_or(ErrorCode::Other, |d| d.code).exit_code())
         }
     }
 
     pub fn run_inner(self) -> Result<()> {
-        let input = self
+        match self.command.unwrap_or(Command::Convert(self.convert)) {
+            Command::Convert(args) => {
+                if let (Some(in_dir), Some(out_dir)) = (&args.in_dir, &args.out_dir) {
+                    return convert_dir(&args, in_dir, out_dir, self.verbose);
+                }
+                let input = args.input.read(args.validate, self.verbose)?;
+                let output_format = args.output_format.unwrap_or(match input.format {
+                    InputFormat::Json => OutputFormat::Json,
+                    InputFormat::Text => OutputFormat::Text,
+                });
+                println!("{}", output_format.render(&input.expr)?);
+            }
+            Command::Validate(args) => {
+                let input = args.read(true, self.verbose)?;
+                for warning in input.expr.lint() {
+                    eprint!("[WARNING] {warning}\n{}", input.highlight(&warning.pointer));
+                }
+            }
+            Command::Sql(args) => {
+                let queryables = args.input.queryables()?;
+                let input = args.input.read(args.validate, self.verbose)?;
+                let mut options = ToSqlOptions {
+                    inline_params: args.sql_params == SqlParams::Inline,
+                    queryables,
+                    ..Default::default()
+                };
+                if let Some(path) = args.mapping {
+                    options = options.with_json(&fs::read_to_string(path)?)?;
+                }
+                if let Some(column) = args.jsonb_column {
+                    let prefix = args.jsonb_prefix.into_iter().filter(|key| !key.is_empty());
+                    options = options.with_jsonb(column, prefix);
+                }
+                let sql = input.expr.to_sql_with_options(&options)?;
+                if options.inline_params {
+                    print!("{}", sql.query);
+                } else {
+                    serde_json::to_writer_pretty(std::io::stdout(), &sql)?;
+                }
+                println!();
+            }
+            Command::Explain(args) => {
+                let input = args.read(false, self.verbose)?;
+                let expr = &input.expr;
+                println!("text: {}", expr.to_text()?);
+                println!("tree:");
+                for line in expr.to_tree()?.lines() {
+                    println!("  {line}");
+                }
+                let report = Validator::default_ref().validate_expr(expr);
+                if report.is_valid() {
+                    println!("valid: yes");
+                } else {
+                    println!("valid: no");
+                    for issue in &report.issues {
+                        println!("  {issue}");
+                    }
+                }
+                let warnings = expr.lint();
+                println!("warnings: {}", warnings.len());
+                for warning in &warnings {
+                    println!("  {warning}");
+                }
+                match expr.spatial_extent()? {
+                    Extent::Bounded(rect) => println!(
+                        "spatial extent: {}, {}, {}, {}",
+                        rect.min().x,
+                        rect.min().y,
+                        rect.max().x,
+                        rect.max().y
+                    ),
+                    Extent::Empty => println!("spatial extent: empty"),
+                    Extent::Unbounded => println!("spatial extent: unbounded"),
+                }
+                match expr.temporal_extent()? {
+                    Extent::Bounded(date_range) => println!("temporal extent: {date_range}"),
+                    Extent::Empty => println!("temporal extent: empty"),
+                    Extent::Unbounded => println!("temporal extent: unbounded"),
+                }
+            }
+            Command::Repl(args) => {
+                let queryables: Option<Queryables> = match args.queryables {
+                    Some(path) => Some(fs::read_to_string(path)?.parse()?),
+                    None => None,
+                };
+                repl(queryables.as_ref())?;
+            }
+            Command::Diff(args) => {
+                let a = Input::parse(args.a, None)?.expr;
+                let b = Input::parse(args.b, None)?.expr;
+                if a == b {
+                    println!("identical");
+                    return Ok(());
+                }
+                let (a, b) = (a.canonicalize(), b.canonicalize());
+                if a == b {
+                    println!("equivalent");
+                    return Ok(());
+                }
+                println!("different");
+                let mut differences = Vec::new();
+                diff(&a, &b, "", &mut differences);
+                for (pointer, a, b) in differences {
+                    println!("at '{pointer}':\n- {}\n+ {}", a.to_text()?, b.to_text()?);
+                }
+                std::process::exit(1);
+            }
+            #[cfg(feature = "search")]
+            Command::Search(args) => {
+                let input = args.input.read(true, self.verbose)?;
+                search(
+                    &args.stac_api,
+                    &args.collections,
+                    args.max_items,
+                    &input.expr,
+                )?;
+            }
+            #[cfg(feature = "generate")]
+            Command::Generate(args) => {
+                let classes: Vec<ExprClass> = args
+                    .classes
+                    .iter()
+                    .map(|class| match class {
+                        GenerateClass::Comparison => ExprClass::Comparison,
+                        GenerateClass::Text => ExprClass::Text,
+                        GenerateClass::Spatial => ExprClass::Spatial,
+                        GenerateClass::Temporal => ExprClass::Temporal,
+                        GenerateClass::Array => ExprClass::Array,
+                    })
+                    .collect();
+                let mut rng = match args.seed {
+                    Some(seed) => fastrand::Rng::with_seed(seed),
+                    None => fastrand::Rng::new(),
+                };
+                let mut bytes = vec![0; 4096];
+                for _ in 0..args.count {
+                    rng.fill(&mut bytes);
+                    let expr = Expr::generate(&mut Unstructured::new(&bytes), &classes)?;
+                    match args.output_format {
+                        GenerateFormat::Text => println!("{}", expr.to_text()?),
+                        GenerateFormat::Json => println!("{}", expr.to_json()?),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts every file in a directory, reporting each file's success or
+/// failure on standard error.
+///
+/// Fails if any file couldn't be converted.
+fn convert_dir(args: &ConvertArgs, in_dir: &Path, out_dir: &Path, verbose: u8) -> Result<()> {
+    let queryables = args.input.queryables()?;
+    fs::create_dir_all(out_dir)?;
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(in_dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut failures = 0;
+    for path in &paths {
+        let result = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| {
+                Input::load(
+                    source,
+                    args.input.input_format.clone(),
+                    args.validate,
+                    verbose,
+                    queryables.as_ref(),
+                )
+            })
+            .and_then(|input| {
+                let output_format = args.output_format.clone().unwrap_or(match input.format {
+                    InputFormat::Json => OutputFormat::Json,
+                    InputFormat::Text => OutputFormat::Text,
+                });
+                let file_name = path.file_stem().unwrap_or_default();
+                let out_path = out_dir
+                    .join(file_name)
+                    .with_extension(output_format.extension());
+                fs::write(&out_path, output_format.render(&input.expr)? + "\n")?;
+                Ok(out_path)
+            });
+        match result {
+            Ok(out_path) => eprintln!("[OK] {} -> {}", path.display(), out_path.display()),
+            Err(err) => {
+                failures += 1;
+                eprintln!("[FAILED] {}\n{err}", path.display());
+            }
+        }
+    }
+    eprintln!("{} converted, {failures} failed", paths.len() - failures);
+    if failures > 0 {
+        Err(anyhow!(
+            "[ERROR] {failures} of {} files failed to convert",
+            paths.len()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads expressions from standard input until it ends or `exit` is typed,
+/// printing each one's conversions, validation status, and SQL.
+///
+/// Errors are printed and don't end the session.
+fn repl(queryables: Option<&Queryables>) -> Result<()> {
+    let validator = Validator::default_ref();
+    let mut stdin = std::io::stdin().lock();
+    let mut line = String::new();
+    loop {
+        print!("cql2> ");
+        std::io::stdout().flush()?;
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let source = line.trim();
+        match source {
+            "" => continue,
+            "exit" | "quit" => return Ok(()),
+            _ => {}
+        }
+        let result = Input::parse(source.to_string(), None).and_then(|input| {
+            let expr = &input.expr;
+            println!("text: {}", expr.to_text()?);
+            println!("json: {}", expr.to_json()?);
+            let report = validator.validate_expr(expr);
+            if report.is_valid() {
+                println!("valid: yes");
+            } else {
+                println!("valid: no");
+                for issue in &report.issues {
+                    print!("  {issue}\n{}", input.highlight(&issue.pointer));
+                }
+            }
+            if let Some(queryables) = queryables {
+                if let Err(err) = input.check_queryables(queryables) {
+                    println!("{err}");
+                }
+            }
+            let sql = expr.to_sql()?;
+            println!("sql: {}", sql.query);
+            println!("params: {:?}", sql.params);
+            Ok(())
+        });
+        if let Err(err) = result {
+            eprintln!("{err}");
+        }
+    }
+}
+
+impl InputArgs {
+    /// Reads and parses the input, then checks it.
+    fn read(self, validate: bool, verbose: u8) -> Result<Input> {
+        let source = self
             .input
+            .clone()
             .and_then(|input| if input == "-" { None } else { Some(input) })
             .map(Ok)
             .unwrap_or_else(read_stdin)?;
-        let input_format = self.input_format.unwrap_or_else(|| {
-            if input.starts_with('{') {
+        let queryables = self.queryables()?;
+        Input::load(
+            source,
+            self.input_format,
+            validate,
+            verbose,
+            queryables.as_ref(),
+        )
+    }
+
+    /// Reads the queryables, if provided.
+    fn queryables(&self) -> Result<Option<Queryables>> {
+        match &self.queryables {
+            Some(path) => Ok(Some(fs::read_to_string(path)?.parse()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Input {
+    /// Parses CQL2, then checks it.
+    fn load(
+        source: String,
+        format: Option<InputFormat>,
+        validate: bool,
+        verbose: u8,
+        queryables: Option<&Queryables>,
+    ) -> Result<Input> {
+        let input = Input::parse(source, format)?;
+        if validate {
+            input.validate(verbose)?;
+        }
+        if let Some(queryables) = queryables {
+            input.check_queryables(queryables)?;
+        }
+        Ok(input)
+    }
+
+    /// Parses CQL2, auto-detecting the format if it isn't provided.
+    fn parse(source: String, format: Option<InputFormat>) -> Result<Input> {
+        let format = format.unwrap_or_else(|| {
+            if source.trim_start().starts_with('{') {
                 InputFormat::Json
             } else {
                 InputFormat::Text
             }
         });
-        let expr: Expr = match input_format {
-            InputFormat::Json => cql2::parse_json(&input)?,
-            InputFormat::Text => match cql2::parse_text(&input) {
-                Ok(expr) => expr,
+        let (expr, source_map) = match format {
+            InputFormat::Json => match cql2::parse_json(&source) {
+                Ok(expr) => (expr, None),
+                Err(err) => {
+                    let offset = byte_offset(&source, err.line(), err.column());
+                    return Err(Diagnostic::single(
+                        ErrorCode::Parse,
+                        format!("[ERROR] Parsing error: {source}\n{err}"),
+                        err.to_string(),
+                        None,
+                        Some(offset..offset),
+                    )
+                    .into());
+                }
+            },
+            InputFormat::Text => match cql2::parse_text_with_source_map(&source) {
+                Ok((expr, source_map)) => (expr, Some(source_map)),
                 Err(err) => {
-                    return Err(anyhow!("[ERROR] Parsing error: {input}\n{err}"));
+                    let message = match &err {
+                        cql2::Error::Pest(error) => error.variant.message().to_string(),
+                        err => err.to_string(),
+                    };
+                    return Err(Diagnostic::single(
+                        ErrorCode::Parse,
+                        format!("[ERROR] Parsing error: {source}\n{err}"),
+                        message,
+                        None,
+                        err.span(),
+                    )
+                    .into());
                 }
             },
         };
-        if self.validate {
-            let validator = Validator::new().unwrap();
-            let value = serde_json::to_value(&expr).unwrap();
-            if let Err(error) = validator.validate(&value) {
-                return Err(anyhow!(
-                    "[ERROR] Invalid CQL2: {input}\n{}",
-                    match self.verbose {
-                        0 => "For more detailed validation information, use -v".to_string(),
-                        1 => format!("For more detailed validation information, use -vv\n{error}"),
-                        2 =>
-                            format!("For more detailed validation information, use -vvv\n{error:#}"),
-                        _ => {
-                            let detailed_output = error.detailed_output();
-                            format!("{detailed_output:#}")
-                        }
-                    }
-                ));
+        Ok(Input {
+            source,
+            format,
+            expr,
+            source_map,
+        })
+    }
+
+    /// Validates the expression against the CQL2 JSON Schema and checks its
+    /// temporal literals.
+    fn validate(&self, verbose: u8) -> Result<()> {
+        let validator = Validator::default_ref();
+        let report = validator.validate_schema(&self.expr);
+        if let Some(issue) = report.issues.first() {
+            // The whole-schema error recurses into the expression, so it's
+            // only computed when asked for.
+            let value = serde_json::to_value(&self.expr).unwrap();
+            let error = if verbose > 0 {
+                validator.validate(&value).err()
+            } else {
+                None
+            };
+            let detail = |f: fn(&ValidationError<'_, '_>) -> String| {
+                error.as_ref().map(f).unwrap_or_default()
+            };
+            let text = format!(
+                "[ERROR] Invalid CQL2: {}\n{}{}",
+                self.source,
+                self.highlight(&issue.pointer),
+                match verbose {
+                    0 => "For more detailed validation information, use -v".to_string(),
+                    1 => format!(
+                        "For more detailed validation information, use -vv\n{}",
+                        detail(|error| error.to_string())
+                    ),
+                    2 => format!(
+                        "For more detailed validation information, use -vvv\n{}",
+                        detail(|error| format!("{error:#}"))
+                    ),
+                    _ => detail(|error| format!("{:#}", error.detailed_output())),
+                }
+            );
+            let problems = validator
+                .validate_expr(&self.expr)
+                .issues
+                .into_iter()
+                .map(|issue| self.problem(issue.message, issue.pointer))
+                .collect();
+            return Err(Diagnostic {
+                code: ErrorCode::Invalid,
+                text,
+                problems,
             }
+            .into());
         }
-        let output_format = self.output_format.unwrap_or(match input_format {
-            InputFormat::Json => OutputFormat::Json,
-            InputFormat::Text => OutputFormat::Text,
-        });
-        match output_format {
-            OutputFormat::JsonPretty => serde_json::to_writer_pretty(std::io::stdout(), &expr)?,
-            OutputFormat::Json => serde_json::to_writer(std::io::stdout(), &expr)?,
-            OutputFormat::Text => print!("{}", expr.to_text()?),
-            OutputFormat::Sql => serde_json::to_writer_pretty(std::io::stdout(), &expr.to_sql()?)?,
+        if let Err(error) = self.expr.check_temporal_literals() {
+            let pointer = error.pointer().unwrap_or_default().to_string();
+            let message = match &error {
+                cql2::Error::InvalidTemporalLiteral {
+                    literal, reason, ..
+                } => format!("invalid temporal literal '{literal}': {reason}"),
+                error => error.to_string(),
+            };
+            return Err(Diagnostic {
+                code: ErrorCode::Invalid,
+                text: format!(
+                    "[ERROR] Invalid CQL2: {}\n{}{error}",
+                    self.source,
+                    self.highlight(&pointer)
+                ),
+                problems: vec![self.problem(message, pointer)],
+            }
+            .into());
         }
-        println!();
         Ok(())
     }
+
+    /// Checks the expression's properties and operand types against
+    /// queryables.
+    fn check_queryables(&self, queryables: &Queryables) -> Result<()> {
+        let unknown = queryables.unknown_properties(&self.expr);
+        if !unknown.is_empty() {
+            return Err(Diagnostic {
+                code: ErrorCode::UnknownProperty,
+                text: format!(
+                    "[ERROR] Unknown properties: {}\n{}",
+                    self.source,
+                    unknown.join(", ")
+                ),
+                problems: unknown
+                    .iter()
+                    .map(|property| Problem {
+                        message: format!("unknown property: {property}"),
+                        pointer: None,
+                        span: None,
+                    })
+                    .collect(),
+            }
+            .into());
+        }
+        let errors = self.expr.type_check(queryables);
+        if !errors.is_empty() {
+            let text: Vec<String> = errors
+                .iter()
+                .map(|error| format!("{}{error}", self.highlight(&error.pointer)))
+                .collect();
+            return Err(Diagnostic {
+                code: ErrorCode::Type,
+                text: format!("[ERROR] Type errors: {}\n{}", self.source, text.join("\n")),
+                problems: errors
+                    .into_iter()
+                    .map(|error| {
+                        let message = format!(
+                            "{} expected {}, but got {}",
+                            error.op, error.expected, error.actual
+                        );
+                        self.problem(message, error.pointer)
+                    })
+                    .collect(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns a problem at a JSON pointer, with its span in the text input if
+    /// possible.
+    fn problem(&self, message: String, pointer: String) -> Problem {
+        Problem {
+            message,
+            span: self
+                .source_map
+                .as_ref()
+                .and_then(|source_map| source_map.span(&pointer)),
+            pointer: Some(pointer),
+        }
+    }
+
+    /// Points at the part of the text input at a JSON pointer, if possible.
+    fn highlight(&self, pointer: &str) -> String {
+        self.source_map
+            .as_ref()
+            .and_then(|source_map| source_map.highlight(&self.source, pointer))
+            .map(|highlight| format!("{highlight}\n"))
+            .unwrap_or_default()
+    }
+}
+
+impl Diagnostic {
+    fn single(
+        code: ErrorCode,
+        text: String,
+        message: String,
+        pointer: Option<String>,
+        span: Option<Range<usize>>,
+    ) -> Diagnostic {
+        Diagnostic {
+            code,
+            text,
+            problems: vec![Problem {
+                message,
+                pointer,
+                span,
+            }],
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl Problem {
+    fn to_json(&self, code: ErrorCode) -> Value {
+        json!({
+            "code": code.as_str(),
+            "message": self.message,
+            "pointer": self.pointer,
+            "span": self.span.as_ref().map(|span| json!({ "start": span.start, "end": span.end })),
+        })
+    }
+}
+
+impl ErrorCode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Other => "error",
+            ErrorCode::Parse => "parse_error",
+            ErrorCode::Invalid => "invalid",
+            ErrorCode::UnknownProperty => "unknown_property",
+            ErrorCode::Type => "type_error",
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::Other => 1,
+            ErrorCode::Parse => 3,
+            ErrorCode::Invalid => 4,
+            ErrorCode::UnknownProperty | ErrorCode::Type => 5,
+        }
+    }
+}
+
+/// Converts a one-based line and column into a byte offset.
+fn byte_offset(source: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + column.saturating_sub(1)).min(source.len())
+}
+
+/// Searches a STAC API and writes the matching items to standard output, one
+/// per line, following `next` links until there are no more pages.
+///
+/// The filter is sent as cql2-json in a POST if the API conforms to cql2-json,
+/// and as cql2-text in a GET otherwise.
+#[cfg(feature = "search")]
+fn search(
+    stac_api: &str,
+    collections: &[String],
+    max_items: Option<usize>,
+    expr: &Expr,
+) -> Result<()> {
+    let root = stac_api.trim_end_matches('/');
+    let landing_page: Value = ureq::get(root).call()?.body_mut().read_json()?;
+    let conforms_to = landing_page["conformsTo"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+    let url = format!("{root}/search");
+    let mut body = json!({ "filter": expr.to_value()?, "filter-lang": FilterLang::Cql2Json });
+    if !collections.is_empty() {
+        body["collections"] = json!(collections);
+    }
+    let mut response = match FilterLang::negotiate(conforms_to) {
+        Some(FilterLang::Cql2Json) => ureq::post(&url).send_json(&body)?,
+        Some(lang @ FilterLang::Cql2Text) => {
+            let mut request = ureq::get(&url)
+                .query("filter", expr.to_text()?)
+                .query("filter-lang", lang.as_str());
+            if !collections.is_empty() {
+                request = request.query("collections", collections.join(","));
+            }
+            request.call()?
+        }
+        None => {
+            return Err(anyhow!(
+                "[ERROR] {root} doesn't conform to cql2-json or cql2-text"
+            ))
+        }
+    };
+    let mut count = 0;
+    loop {
+        let page: Value = response.body_mut().read_json()?;
+        for item in page["features"].as_array().into_iter().flatten() {
+            if max_items.is_some_and(|max_items| count >= max_items) {
+                return Ok(());
+            }
+            println!("{}", serde_json::to_string(item)?);
+            count += 1;
+        }
+        let Some(next) = page["links"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|link| link["rel"] == "next")
+        else {
+            return Ok(());
+        };
+        let href = next["href"]
+            .as_str()
+            .ok_or_else(|| anyhow!("[ERROR] next link without an href: {next}"))?;
+        response = if next["method"] == "POST" {
+            // The link's body replaces the request body, unless it should be
+            // merged into it.
+            match (next["merge"] == true, next["body"].as_object()) {
+                (true, Some(next_body)) => {
+                    for (key, value) in next_body {
+                        body[key] = value.clone();
+                    }
+                }
+                (false, Some(_)) => body = next["body"].clone(),
+                (_, None) => {}
+            }
+            ureq::post(href).send_json(&body)?
+        } else {
+            ureq::get(href).call()?
+        };
+    }
+}
+
+/// Collects the smallest differing subtrees of two expressions, with JSON
+/// pointers to where they are.
+fn diff<'a>(a: &'a Expr, b: &'a Expr, pointer: &str, out: &mut Vec<(String, &'a Expr, &'a Expr)>) {
+    if a == b {
+        return;
+    }
+    match (a, b) {
+        (
+            Expr::Operation { op, args },
+            Expr::Operation {
+                op: other_op,
+                args: other_args,
+            },
+        ) if op == other_op && args.len() == other_args.len() => {
+            for (i, (a, b)) in args.iter().zip(other_args).enumerate() {
+                diff(a, b, &format!("{pointer}/args/{i}"), out);
+            }
+        }
+        (Expr::Array(a), Expr::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                diff(a, b, &format!("{pointer}/{i}"), out);
+            }
+        }
+        _ => out.push((pointer.to_string(), a, b)),
+    }
 }
 
 fn read_stdin() -> Result<String> {
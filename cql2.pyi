@@ -1,11 +1,12 @@
-from typing import Any
+from typing import IO, Any, Iterator, TypedDict
 from os import PathLike
 
-def parse_file(path: PathLike | str) -> Expr:
-    """Parses CQL2 from a filesystem path.
+def parse_file(file: PathLike | str | IO[str] | IO[bytes]) -> Expr:
+    """Parses CQL2 from a filesystem path or an open file.
 
     Args:
-        path (PathLike | str): The input path
+        file (PathLike | str | IO[str] | IO[bytes]): The input path, or a file
+            object opened in text or binary mode, e.g. `io.StringIO`
 
     Returns:
         Expr: The CQL2 expression
@@ -49,6 +50,152 @@ def parse_json(s: str) -> Expr:
         >>> expr = Expr.parse_json('{"op":"=","args":[{"property":"landsat:scene_id"},"LC82030282019133LGN00"]}')
     """
 
+def prop(name: str) -> Expr:
+    """Returns an expression that refers to a property.
+
+    Args:
+        name (str): The property name
+
+    Returns:
+        Expr: The property
+
+    Examples:
+        >>> import cql2
+        >>> cql2.prop("eo:cloud_cover").to_json()
+        {'property': 'eo:cloud_cover'}
+    """
+
+def literal(value: Any) -> Expr:
+    """Returns an expression for a value.
+
+    Strings are always string literals, never cql2-text, so untrusted input
+    can't change the structure of an expression. Dictionaries are read as
+    cql2-json, e.g. GeoJSON geometries or `{"bbox": [...]}`.
+
+    Args:
+        value (Any): The value
+
+    Returns:
+        Expr: The value as an expression
+    """
+
+def op(op: str, *args: Expr | Any) -> Expr:
+    """Returns an expression that applies an operator to arguments.
+
+    Arguments that aren't already expressions are converted with `literal`.
+
+    Args:
+        op (str): The operator, e.g. `"="` or `"like"`
+        args (Expr | Any): The arguments
+
+    Returns:
+        Expr: The operation
+
+    Examples:
+        >>> import cql2
+        >>> cql2.op("=", cql2.prop("platform"), "landsat-8").to_text()
+        "(platform = 'landsat-8')"
+    """
+
+def and_(*args: Expr | Any) -> Expr:
+    """Returns the `AND` of one or more expressions."""
+
+def or_(*args: Expr | Any) -> Expr:
+    """Returns the `OR` of one or more expressions."""
+
+def not_(arg: Expr | Any) -> Expr:
+    """Returns the `NOT` of an expression."""
+
+def timestamp(value: str) -> Expr:
+    """Returns a timestamp, e.g. `timestamp("2020-01-01T00:00:00Z")`."""
+
+def date(value: str) -> Expr:
+    """Returns a date, e.g. `date("2020-01-01")`."""
+
+def interval(start: Expr | Any, end: Expr | Any) -> Expr:
+    """Returns an interval between two instants. Use `".."` for an open end."""
+
+def s_contains(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_contains(a, b)`."""
+
+def s_crosses(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_crosses(a, b)`."""
+
+def s_disjoint(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_disjoint(a, b)`."""
+
+def s_equals(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_equals(a, b)`."""
+
+def s_intersects(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_intersects(a, b)`."""
+
+def s_overlaps(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_overlaps(a, b)`."""
+
+def s_touches(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_touches(a, b)`."""
+
+def s_within(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `s_within(a, b)`."""
+
+def t_after(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_after(a, b)`."""
+
+def t_before(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_before(a, b)`."""
+
+def t_contains(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_contains(a, b)`."""
+
+def t_disjoint(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_disjoint(a, b)`."""
+
+def t_during(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_during(a, b)`."""
+
+def t_equals(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_equals(a, b)`."""
+
+def t_finishedby(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_finishedby(a, b)`."""
+
+def t_finishes(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_finishes(a, b)`."""
+
+def t_intersects(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_intersects(a, b)`."""
+
+def t_meets(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_meets(a, b)`."""
+
+def t_metby(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_metby(a, b)`."""
+
+def t_overlappedby(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_overlappedby(a, b)`."""
+
+def t_overlaps(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_overlaps(a, b)`."""
+
+def t_startedby(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_startedby(a, b)`."""
+
+def t_starts(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `t_starts(a, b)`."""
+
+def a_containedby(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `a_containedby(a, b)`."""
+
+def a_contains(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `a_contains(a, b)`."""
+
+def a_equals(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `a_equals(a, b)`."""
+
+def a_overlaps(a: Expr | Any, b: Expr | Any) -> Expr:
+    """Returns `a_overlaps(a, b)`."""
+
 class Expr:
     def __init__(self, cql2: str | dict[str, Any]) -> None:
         """A CQL2 expression.
@@ -118,6 +265,54 @@ class Expr:
             ['LC82030282019133LGN00']
         """
 
+    def properties(self) -> list[str]:
+        """Returns the names of the properties in this expression.
+
+        Properties are in the order they first appear, without duplicates.
+
+        Returns:
+            list[str]: The property names
+
+        Examples:
+            >>> from cql2 import Expr
+            >>> Expr("eo:cloud_cover < 10 AND s_intersects(geometry, POINT(0 0))").properties()
+            ['eo:cloud_cover', 'geometry']
+        """
+
+    def operators(self) -> list[str]:
+        """Returns the operators and functions in this expression.
+
+        Operators are in the order they first appear, without duplicates.
+
+        Returns:
+            list[str]: The operators
+
+        Examples:
+            >>> from cql2 import Expr
+            >>> Expr("eo:cloud_cover < 10 AND s_intersects(geometry, POINT(0 0))").operators()
+            ['and', '<', 's_intersects']
+        """
+
+    def to_pandas_query(self) -> str:
+        """Converts this cql2 expression to a pandas `DataFrame.query` string.
+
+        Comparisons, arithmetic, `IN`, `BETWEEN`, `AND`, `OR`, and `NOT` are
+        supported.
+
+        Returns:
+            str: The pandas query
+
+        Raises:
+            ValueError: Raised if the expression uses spatial, temporal,
+                array, `LIKE`, or `IS NULL` operators
+
+        Examples:
+            >>> from cql2 import Expr
+            >>> expr = Expr("eo:cloud_cover < 10 AND platform IN ('landsat-8', 'landsat-9')")
+            >>> expr.to_pandas_query()
+            "((`eo:cloud_cover` < 10) and (`platform` in ['landsat-8', 'landsat-9']))"
+        """
+
 class SqlQuery:
     """A SQL query"""
 
@@ -127,8 +322,44 @@ class SqlQuery:
     params: list[str]
     """The parameters, to use for binding."""
 
+    def __iter__(self) -> Iterator[str | list[str]]:
+        """Iterates over the query and the parameters.
+
+        Examples:
+            >>> from cql2 import Expr
+            >>> query, params = Expr("landsat:scene_id = 'LC82030282019133LGN00'").to_sql()
+        """
+
+    def asdict(self) -> dict[str, Any]:
+        """Returns the query and parameters as a dictionary.
+
+        Returns:
+            dict[str, Any]: A dictionary with `query` and `params` keys
+        """
+
 class ParseError(Exception):
     """An error raised when cql2 parsing fails."""
 
+    line: int | None
+    """The line where parsing failed, starting at one, or `None` if unknown."""
+
+    column: int | None
+    """The column where parsing failed, starting at one, or `None` if unknown."""
+
+class ValidationErrorDetail(TypedDict):
+    """One problem found by validation."""
+
+    json_pointer: str
+    """A JSON pointer to the problem in the cql2-json."""
+
+    message: str
+    """A description of the problem."""
+
 class ValidationError(Exception):
     """An error raised when cql2 json-schema validation fails."""
+
+    json_pointer: str | None
+    """A JSON pointer to the first problem in the cql2-json."""
+
+    errors: list[ValidationErrorDetail]
+    """Every problem in the expression."""
@@ -0,0 +1,169 @@
+#ifndef CQL2_H
+#define CQL2_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The result of a fallible function.
+typedef enum Cql2Status {
+  // The function succeeded.
+  CQL2_OK = 0,
+  // A required pointer argument was null.
+  CQL2_NULL_ARGUMENT = 1,
+  // A string argument wasn't valid UTF-8.
+  CQL2_INVALID_UTF8 = 2,
+  // The input couldn't be parsed as cql2-text or cql2-json.
+  CQL2_PARSE_ERROR = 3,
+  // The expression doesn't conform to the CQL2 JSON Schema.
+  CQL2_INVALID = 4,
+  // The expression couldn't be converted to the requested format.
+  CQL2_CONVERSION_ERROR = 5,
+  // The library panicked. This is a bug, please report it.
+  CQL2_PANIC = 6,
+} Cql2Status;
+
+// A parsed CQL2 expression.
+typedef struct Cql2Expr Cql2Expr;
+
+// A SQL query with its parameters.
+typedef struct Cql2SqlQuery Cql2SqlQuery;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns a description of the last error on this thread, or null if there
+// hasn't been one.
+//
+// The string is owned by the library and is valid until the next call into
+// the library on this thread. Don't free it.
+const char *cql2_last_error(void);
+
+// Parses cql2-text or cql2-json, detecting which from the input.
+//
+// On success, `*out` is set to a new expression that must be freed with
+// `cql2_expr_free`.
+//
+// # Safety
+//
+// `s` must be null or a valid, NUL-terminated string, and `out` must be null
+// or valid for writes.
+enum Cql2Status cql2_parse(const char *s, struct Cql2Expr **out);
+
+// Parses cql2-text.
+//
+// # Safety
+//
+// The same as `cql2_parse`.
+enum Cql2Status cql2_parse_text(const char *s, struct Cql2Expr **out);
+
+// Parses cql2-json.
+//
+// # Safety
+//
+// The same as `cql2_parse`.
+enum Cql2Status cql2_parse_json(const char *s, struct Cql2Expr **out);
+
+// Frees an expression. Does nothing if `expr` is null.
+//
+// # Safety
+//
+// `expr` must be null or a pointer returned by one of the parse functions
+// that hasn't already been freed.
+void cql2_expr_free(struct Cql2Expr *expr);
+
+// Validates an expression against the CQL2 JSON Schema.
+//
+// Returns `CQL2_INVALID` if the expression is invalid, with every problem in
+// `cql2_last_error`, one per line.
+//
+// # Safety
+//
+// `expr` must be null or a valid expression.
+enum Cql2Status cql2_validate(const struct Cql2Expr *expr);
+
+// Converts an expression to cql2-text.
+//
+// On success, `*out` is set to a new string that must be freed with
+// `cql2_string_free`.
+//
+// # Safety
+//
+// `expr` must be null or a valid expression, and `out` must be null or
+// valid for writes.
+enum Cql2Status cql2_to_text(const struct Cql2Expr *expr, char **out);
+
+// Converts an expression to cql2-json.
+//
+// # Safety
+//
+// The same as `cql2_to_text`.
+enum Cql2Status cql2_to_json(const struct Cql2Expr *expr, char **out);
+
+// Converts an expression to a SQL query with the default options.
+//
+// On success, `*out` is set to a new query that must be freed with
+// `cql2_sql_query_free`.
+//
+// # Safety
+//
+// `expr` must be null or a valid expression, and `out` must be null or
+// valid for writes.
+enum Cql2Status cql2_to_sql(const struct Cql2Expr *expr, struct Cql2SqlQuery **out);
+
+// Returns the query of a SQL query, with `$1`, `$2`, ... placeholders for
+// its parameters.
+//
+// The string is owned by the query. Don't free it. Returns null if `sql` is
+// null.
+//
+// # Safety
+//
+// `sql` must be null or a valid SQL query.
+const char *cql2_sql_query_query(const struct Cql2SqlQuery *sql);
+
+// Returns the number of parameters of a SQL query, or zero if `sql` is
+// null.
+//
+// # Safety
+//
+// `sql` must be null or a valid SQL query.
+size_t cql2_sql_query_params_len(const struct Cql2SqlQuery *sql);
+
+// Returns a parameter of a SQL query, or null if `sql` is null or `index` is
+// out of range.
+//
+// Parameters are numbered from zero, so parameter `0` is `$1`. The string is
+// owned by the query. Don't free it.
+//
+// # Safety
+//
+// `sql` must be null or a valid SQL query.
+const char *cql2_sql_query_param(const struct Cql2SqlQuery *sql, size_t index);
+
+// Frees a SQL query. Does nothing if `sql` is null.
+//
+// # Safety
+//
+// `sql` must be null or a pointer returned by `cql2_to_sql` that hasn't
+// already been freed.
+void cql2_sql_query_free(struct Cql2SqlQuery *sql);
+
+// Frees a string returned by the library. Does nothing if `s` is null.
+//
+// # Safety
+//
+// `s` must be null or a string returned by `cql2_to_text` or `cql2_to_json`
+// that hasn't already been freed.
+void cql2_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CQL2_H */
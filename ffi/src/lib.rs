@@ -0,0 +1,523 @@
+//! C bindings for [cql2](https://docs.rs/cql2).
+//!
+//! Expressions and SQL queries are opaque handles that must be freed with
+//! `cql2_expr_free` and `cql2_sql_query_free`. Strings returned by the
+//! library must be freed with `cql2_string_free`. Functions that can fail
+//! return a `Cql2Status`, and `cql2_last_error` describes the most recent
+//! failure on the calling thread. Panics are caught before they reach the
+//! caller and reported as `CQL2_PANIC`.
+//!
+//! The doc comments use C names, because they are copied into the header.
+//!
+//! The header is generated by [cbindgen](https://github.com/mozilla/cbindgen)
+//! and checked in as `include/cql2.h`.
+
+#![allow(clippy::result_large_err)]
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use cql2::{Expr, SqlQuery, Validator};
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The result of a fallible function.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cql2Status {
+    /// The function succeeded.
+    Cql2Ok = 0,
+
+    /// A required pointer argument was null.
+    Cql2NullArgument = 1,
+
+    /// A string argument wasn't valid UTF-8.
+    Cql2InvalidUtf8 = 2,
+
+    /// The input couldn't be parsed as cql2-text or cql2-json.
+    Cql2ParseError = 3,
+
+    /// The expression doesn't conform to the CQL2 JSON Schema.
+    Cql2Invalid = 4,
+
+    /// The expression couldn't be converted to the requested format.
+    Cql2ConversionError = 5,
+
+    /// The library panicked. This is a bug, please report it.
+    Cql2Panic = 6,
+}
+
+/// A parsed CQL2 expression.
+#[derive(Debug)]
+pub struct Cql2Expr(Expr);
+
+/// A SQL query with its parameters.
+#[derive(Debug)]
+pub struct Cql2SqlQuery {
+    query: CString,
+    params: Vec<CString>,
+}
+
+/// Returns a description of the last error on this thread, or null if there
+/// hasn't been one.
+///
+/// The string is owned by the library and is valid until the next call into
+/// the library on this thread. Don't free it.
+#[no_mangle]
+pub extern "C" fn cql2_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Parses cql2-text or cql2-json, detecting which from the input.
+///
+/// On success, `*out` is set to a new expression that must be freed with
+/// `cql2_expr_free`.
+///
+/// # Safety
+///
+/// `s` must be null or a valid, NUL-terminated string, and `out` must be null
+/// or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_parse(s: *const c_char, out: *mut *mut Cql2Expr) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    catch_panic(|| unsafe { parse_with(s, out, str::parse) })
+}
+
+/// Parses cql2-text.
+///
+/// # Safety
+///
+/// The same as `cql2_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_parse_text(s: *const c_char, out: *mut *mut Cql2Expr) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    catch_panic(|| unsafe { parse_with(s, out, cql2::parse_text) })
+}
+
+/// Parses cql2-json.
+///
+/// # Safety
+///
+/// The same as `cql2_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_parse_json(s: *const c_char, out: *mut *mut Cql2Expr) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    catch_panic(|| unsafe {
+        parse_with(s, out, |s| cql2::parse_json(s).map_err(cql2::Error::from))
+    })
+}
+
+/// Frees an expression. Does nothing if `expr` is null.
+///
+/// # Safety
+///
+/// `expr` must be null or a pointer returned by one of the parse functions
+/// that hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_expr_free(expr: *mut Cql2Expr) {
+    if !expr.is_null() {
+        // SAFETY: the pointer came from Box::into_raw in parse_with.
+        drop(unsafe { Box::from_raw(expr) });
+    }
+}
+
+/// Validates an expression against the CQL2 JSON Schema.
+///
+/// Returns `CQL2_INVALID` if the expression is invalid, with every problem in
+/// `cql2_last_error`, one per line.
+///
+/// # Safety
+///
+/// `expr` must be null or a valid expression.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_validate(expr: *const Cql2Expr) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    let Some(expr) = (unsafe { expr.as_ref() }) else {
+        return null_argument("expr");
+    };
+    catch_panic(|| {
+        let report = Validator::default_ref().validate_expr(&expr.0);
+        if report.is_valid() {
+            Cql2Status::Cql2Ok
+        } else {
+            let issues: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+            fail(Cql2Status::Cql2Invalid, issues.join("\n"))
+        }
+    })
+}
+
+/// Converts an expression to cql2-text.
+///
+/// On success, `*out` is set to a new string that must be freed with
+/// `cql2_string_free`.
+///
+/// # Safety
+///
+/// `expr` must be null or a valid expression, and `out` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_to_text(expr: *const Cql2Expr, out: *mut *mut c_char) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    catch_panic(|| unsafe { convert_with(expr, out, Expr::to_text) })
+}
+
+/// Converts an expression to cql2-json.
+///
+/// # Safety
+///
+/// The same as `cql2_to_text`.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_to_json(expr: *const Cql2Expr, out: *mut *mut c_char) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    catch_panic(|| unsafe { convert_with(expr, out, Expr::to_json) })
+}
+
+/// Converts an expression to a SQL query with the default options.
+///
+/// On success, `*out` is set to a new query that must be freed with
+/// `cql2_sql_query_free`.
+///
+/// # Safety
+///
+/// `expr` must be null or a valid expression, and `out` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_to_sql(
+    expr: *const Cql2Expr,
+    out: *mut *mut Cql2SqlQuery,
+) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    let Some(expr) = (unsafe { expr.as_ref() }) else {
+        return null_argument("expr");
+    };
+    if out.is_null() {
+        return null_argument("out");
+    }
+    catch_panic(|| {
+        let SqlQuery { query, params } = match expr.0.to_sql() {
+            Ok(sql) => sql,
+            Err(err) => return fail(Cql2Status::Cql2ConversionError, err.to_string()),
+        };
+        let sql = Cql2SqlQuery {
+            query: to_c_string(query),
+            params: params.into_iter().map(to_c_string).collect(),
+        };
+        // SAFETY: checked for null above, and guaranteed writable by the
+        // caller.
+        unsafe { *out = Box::into_raw(Box::new(sql)) };
+        Cql2Status::Cql2Ok
+    })
+}
+
+/// Returns the query of a SQL query, with `$1`, `$2`, ... placeholders for
+/// its parameters.
+///
+/// The string is owned by the query. Don't free it. Returns null if `sql` is
+/// null.
+///
+/// # Safety
+///
+/// `sql` must be null or a valid SQL query.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_sql_query_query(sql: *const Cql2SqlQuery) -> *const c_char {
+    // SAFETY: guaranteed by the caller.
+    match unsafe { sql.as_ref() } {
+        Some(sql) => sql.query.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Returns the number of parameters of a SQL query, or zero if `sql` is
+/// null.
+///
+/// # Safety
+///
+/// `sql` must be null or a valid SQL query.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_sql_query_params_len(sql: *const Cql2SqlQuery) -> usize {
+    // SAFETY: guaranteed by the caller.
+    unsafe { sql.as_ref() }.map_or(0, |sql| sql.params.len())
+}
+
+/// Returns a parameter of a SQL query, or null if `sql` is null or `index` is
+/// out of range.
+///
+/// Parameters are numbered from zero, so parameter `0` is `$1`. The string is
+/// owned by the query. Don't free it.
+///
+/// # Safety
+///
+/// `sql` must be null or a valid SQL query.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_sql_query_param(
+    sql: *const Cql2SqlQuery,
+    index: usize,
+) -> *const c_char {
+    // SAFETY: guaranteed by the caller.
+    match unsafe { sql.as_ref() }.and_then(|sql| sql.params.get(index)) {
+        Some(param) => param.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Frees a SQL query. Does nothing if `sql` is null.
+///
+/// # Safety
+///
+/// `sql` must be null or a pointer returned by `cql2_to_sql` that hasn't
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_sql_query_free(sql: *mut Cql2SqlQuery) {
+    if !sql.is_null() {
+        // SAFETY: the pointer came from Box::into_raw in cql2_to_sql.
+        drop(unsafe { Box::from_raw(sql) });
+    }
+}
+
+/// Frees a string returned by the library. Does nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by `cql2_to_text` or `cql2_to_json`
+/// that hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cql2_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: the pointer came from CString::into_raw in convert_with.
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+unsafe fn parse_with(
+    s: *const c_char,
+    out: *mut *mut Cql2Expr,
+    parse: impl FnOnce(&str) -> Result<Expr, cql2::Error>,
+) -> Cql2Status {
+    if s.is_null() {
+        return null_argument("s");
+    }
+    if out.is_null() {
+        return null_argument("out");
+    }
+    // SAFETY: checked for null above, and guaranteed NUL-terminated by the
+    // caller.
+    let s = match unsafe { CStr::from_ptr(s) }.to_str() {
+        Ok(s) => s,
+        Err(err) => return fail(Cql2Status::Cql2InvalidUtf8, err.to_string()),
+    };
+    match parse(s) {
+        Ok(expr) => {
+            // SAFETY: checked for null above, and guaranteed writable by the
+            // caller.
+            unsafe { *out = Box::into_raw(Box::new(Cql2Expr(expr))) };
+            Cql2Status::Cql2Ok
+        }
+        Err(err) => fail(Cql2Status::Cql2ParseError, err.to_string()),
+    }
+}
+
+unsafe fn convert_with(
+    expr: *const Cql2Expr,
+    out: *mut *mut c_char,
+    convert: impl FnOnce(&Expr) -> Result<String, cql2::Error>,
+) -> Cql2Status {
+    // SAFETY: guaranteed by the caller.
+    let Some(expr) = (unsafe { expr.as_ref() }) else {
+        return null_argument("expr");
+    };
+    if out.is_null() {
+        return null_argument("out");
+    }
+    match convert(&expr.0) {
+        Ok(s) => {
+            // SAFETY: checked for null above, and guaranteed writable by the
+            // caller.
+            unsafe { *out = to_c_string(s).into_raw() };
+            Cql2Status::Cql2Ok
+        }
+        Err(err) => fail(Cql2Status::Cql2ConversionError, err.to_string()),
+    }
+}
+
+/// Runs the body of an exported function, reporting a panic as `CQL2_PANIC`
+/// instead of unwinding into the caller, which would abort the process.
+fn catch_panic(f: impl FnOnce() -> Cql2Status) -> Cql2Status {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| fail(Cql2Status::Cql2Panic, panic_message(payload)))
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    };
+    format!("panicked: {message}")
+}
+
+/// Converts a string to a C string, dropping any NUL bytes, which C can't
+/// represent.
+fn to_c_string(s: String) -> CString {
+    CString::new(s).unwrap_or_else(|err| {
+        let mut bytes = err.into_vec();
+        bytes.retain(|b| *b != 0);
+        CString::new(bytes).expect("NUL bytes were removed")
+    })
+}
+
+fn null_argument(name: &str) -> Cql2Status {
+    fail(Cql2Status::Cql2NullArgument, format!("{name} is null"))
+}
+
+fn fail(status: Cql2Status, message: String) -> Cql2Status {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(to_c_string(message)));
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &CStr) -> *mut Cql2Expr {
+        let mut expr = ptr::null_mut();
+        assert_eq!(
+            unsafe { cql2_parse(s.as_ptr(), &mut expr) },
+            Cql2Status::Cql2Ok
+        );
+        expr
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(cql2_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let expr = parse(c"landsat:scene_id = 'LC82030282019133LGN00'");
+        assert_eq!(unsafe { cql2_validate(expr) }, Cql2Status::Cql2Ok);
+        let mut text = ptr::null_mut();
+        assert_eq!(unsafe { cql2_to_text(expr, &mut text) }, Cql2Status::Cql2Ok);
+        assert_eq!(
+            unsafe { CStr::from_ptr(text) },
+            c"(\"landsat:scene_id\" = 'LC82030282019133LGN00')"
+        );
+        let mut json = ptr::null_mut();
+        assert_eq!(unsafe { cql2_to_json(expr, &mut json) }, Cql2Status::Cql2Ok);
+        let mut from_json = ptr::null_mut();
+        assert_eq!(
+            unsafe { cql2_parse_json(json, &mut from_json) },
+            Cql2Status::Cql2Ok
+        );
+        assert_eq!(unsafe { &(*expr).0 }, unsafe { &(*from_json).0 });
+        unsafe {
+            cql2_string_free(text);
+            cql2_string_free(json);
+            cql2_expr_free(from_json);
+            cql2_expr_free(expr);
+        }
+    }
+
+    #[test]
+    fn sql() {
+        let expr = parse(c"a = 'b' AND c < 1");
+        let mut sql = ptr::null_mut();
+        assert_eq!(unsafe { cql2_to_sql(expr, &mut sql) }, Cql2Status::Cql2Ok);
+        unsafe {
+            assert_eq!(
+                CStr::from_ptr(cql2_sql_query_query(sql)),
+                c"((\"a\" = $1) AND (\"c\" < $2))"
+            );
+            assert_eq!(cql2_sql_query_params_len(sql), 2);
+            assert_eq!(CStr::from_ptr(cql2_sql_query_param(sql, 1)), c"1");
+            assert!(cql2_sql_query_param(sql, 2).is_null());
+            cql2_sql_query_free(sql);
+            cql2_expr_free(expr);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut expr = ptr::null_mut();
+        assert_eq!(
+            unsafe { cql2_parse_text(c"(a = 1".as_ptr(), &mut expr) },
+            Cql2Status::Cql2ParseError
+        );
+        assert!(expr.is_null());
+        assert!(!last_error().is_empty());
+        assert_eq!(
+            unsafe { cql2_parse(ptr::null(), &mut expr) },
+            Cql2Status::Cql2NullArgument
+        );
+        assert_eq!(last_error(), "s is null");
+
+        let expr = parse(c"a = ");
+        assert_eq!(unsafe { cql2_validate(expr) }, Cql2Status::Cql2Invalid);
+        assert!(!last_error().is_empty());
+        unsafe { cql2_expr_free(expr) };
+    }
+
+    #[test]
+    fn wrong_number_of_arguments() {
+        let mut expr = ptr::null_mut();
+        assert_eq!(
+            unsafe {
+                cql2_parse_json(
+                    cr#"{"op": "=", "args": [{"property": "a"}]}"#.as_ptr(),
+                    &mut expr,
+                )
+            },
+            Cql2Status::Cql2Ok
+        );
+        let mut sql = ptr::null_mut();
+        assert_eq!(
+            unsafe { cql2_to_sql(expr, &mut sql) },
+            Cql2Status::Cql2ConversionError
+        );
+        assert!(sql.is_null());
+        unsafe { cql2_expr_free(expr) };
+    }
+
+    #[test]
+    fn panics() {
+        assert_eq!(catch_panic(|| panic!("oops")), Cql2Status::Cql2Panic);
+        assert_eq!(last_error(), "panicked: oops");
+    }
+
+    #[test]
+    fn null_sql_query() {
+        unsafe {
+            assert!(cql2_sql_query_query(ptr::null()).is_null());
+            assert_eq!(cql2_sql_query_params_len(ptr::null()), 0);
+            assert!(cql2_sql_query_param(ptr::null(), 0).is_null());
+        }
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut header = Vec::new();
+        cbindgen::generate(dir)
+            .expect("the header should generate")
+            .write(&mut header);
+        let expected = std::fs::read_to_string(format!("{dir}/include/cql2.h")).unwrap();
+        assert_eq!(
+            String::from_utf8(header).unwrap(),
+            expected,
+            "include/cql2.h is out of date, regenerate it with `cbindgen ffi -o ffi/include/cql2.h`"
+        );
+    }
+}
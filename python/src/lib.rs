@@ -4,11 +4,22 @@ use pyo3::{
     create_exception,
     exceptions::{PyException, PyIOError, PyValueError},
     prelude::*,
+    types::{PyBytes, PyDict, PyIterator},
 };
 use std::path::PathBuf;
 
-create_exception!(cql2, ValidationError, PyException);
-create_exception!(cql2, ParseError, PyException);
+create_exception!(
+    cql2,
+    ValidationError,
+    PyException,
+    "Invalid CQL2.\n\n`json_pointer` is a JSON pointer to the first problem in the cql2-json, and `errors` is a list of every problem, as dictionaries with `json_pointer` and `message` keys."
+);
+create_exception!(
+    cql2,
+    ParseError,
+    PyException,
+    "CQL2 that couldn't be parsed.\n\n`line` and `column` are where parsing failed, starting at one, or `None` if unknown."
+);
 
 /// Crate-specific error enum.
 #[allow(clippy::large_enum_variant)]
@@ -34,23 +45,81 @@ struct SqlQuery {
     params: Vec<String>,
 }
 
+/// Parses a file containing cql2-text or cql2-json.
+///
+/// `file` can be a path, an `os.PathLike`, or an open file object in text or
+/// binary mode, e.g. `io.StringIO`.
 #[pyfunction]
-fn parse_file(path: PathBuf) -> Result<Expr> {
-    ::cql2::parse_file(path).map(Expr).map_err(Error::from)
+fn parse_file(file: Bound<'_, PyAny>) -> PyResult<Expr> {
+    if file.hasattr("read")? {
+        let contents = file.call_method0("read")?;
+        let s = if let Ok(bytes) = contents.downcast::<PyBytes>() {
+            String::from_utf8(bytes.as_bytes().to_vec())
+                .map_err(|err| PyValueError::new_err(err.to_string()))?
+        } else {
+            contents.extract::<String>()?
+        };
+        Ok(s.parse().map(Expr).map_err(Error::from)?)
+    } else {
+        let path: PathBuf = file.extract()?;
+        Ok(::cql2::parse_file(path).map(Expr).map_err(Error::from)?)
+    }
 }
 
 #[pyfunction]
-fn parse_json(s: &str) -> PyResult<Expr> {
+fn parse_json(py: Python<'_>, s: &str) -> PyResult<Expr> {
     ::cql2::parse_json(s)
         .map(Expr)
-        .map_err(|err| ParseError::new_err(err.to_string()))
+        .map_err(|err| parse_error(py, ::cql2::Error::from(err)))
 }
 
 #[pyfunction]
-fn parse_text(s: &str) -> PyResult<Expr> {
+fn parse_text(py: Python<'_>, s: &str) -> PyResult<Expr> {
     ::cql2::parse_text(s)
         .map(Expr)
-        .map_err(|err| ParseError::new_err(err.to_string()))
+        .map_err(|err| parse_error(py, err))
+}
+
+/// Creates a [ParseError] with the location of the error.
+fn parse_error(py: Python<'_>, error: ::cql2::Error) -> PyErr {
+    let (line, column) = error.line_column().unzip();
+    let err = ParseError::new_err(error.to_string());
+    let value = err.value(py);
+    match value
+        .setattr("line", line)
+        .and_then(|()| value.setattr("column", column))
+    {
+        Ok(()) => err,
+        Err(err) => err,
+    }
+}
+
+/// Creates a [ValidationError] with every problem in the expression.
+fn validation_error(py: Python<'_>, message: String, expr: &::cql2::Expr) -> PyErr {
+    let report = ::cql2::Validator::default_ref().validate_expr(expr);
+    let err = ValidationError::new_err(message);
+    let value = err.value(py);
+    let result = report
+        .issues
+        .iter()
+        .map(|issue| {
+            let dict = PyDict::new(py);
+            dict.set_item("json_pointer", &issue.pointer)?;
+            dict.set_item("message", &issue.message)?;
+            Ok(dict)
+        })
+        .collect::<PyResult<Vec<_>>>()
+        .and_then(|errors| {
+            value.setattr(
+                "json_pointer",
+                report.issues.first().map(|issue| &issue.pointer),
+            )?;
+            value.setattr("errors", errors)
+        });
+    match result {
+        Ok(()) => err,
+        Err(err) => err,
+    }
 }
 
 #[pymethods]
@@ -65,10 +134,35 @@ impl Expr {
         }
     }
 
-    fn validate(&self) -> PyResult<()> {
-        let validator = ::cql2::Validator::new().map_err(Error::from)?;
-        if let Err(error) = validator.validate(&self.0.to_value().map_err(Error::from)?) {
-            Err(ValidationError::new_err(error.to_string()))
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let text = self.0.to_text().map_err(Error::from)?;
+        Ok(format!("Expr({})", text.into_pyobject(py)?.repr()?))
+    }
+
+    fn __str__(&self) -> Result<String> {
+        self.0.to_text().map_err(Error::from)
+    }
+
+    fn _repr_html_(&self) -> Result<String> {
+        self.0
+            .to_tree()
+            .map(|tree| tree_html(&tree))
+            .map_err(Error::from)
+    }
+
+    fn _repr_pretty_(&self, p: Bound<'_, PyAny>, _cycle: bool) -> PyResult<()> {
+        let tree = self.0.to_tree().map_err(Error::from)?;
+        p.call_method1("text", (tree,))?;
+        Ok(())
+    }
+
+    fn validate(&self, py: Python<'_>) -> PyResult<()> {
+        let validator = ::cql2::Validator::default_ref();
+        let report = validator.validate_schema(&self.0);
+        if !report.is_valid() {
+            Err(validation_error(py, report.to_string(), &self.0))
+        } else if let Err(error) = self.0.check_temporal_literals() {
+            Err(validation_error(py, error.to_string(), &self.0))
         } else {
             Ok(())
         }
@@ -85,6 +179,298 @@ impl Expr {
     fn to_sql(&self) -> Result<SqlQuery> {
         self.0.to_sql().map(SqlQuery::from).map_err(Error::from)
     }
+
+    /// Returns the names of the properties in this expression, in the order
+    /// they first appear, without duplicates.
+    fn properties(&self) -> Vec<&str> {
+        self.0.properties()
+    }
+
+    /// Returns the operators and functions in this expression, in the order
+    /// they first appear, without duplicates.
+    fn operators(&self) -> Vec<&str> {
+        self.0.operators()
+    }
+
+    /// Converts this expression to a pandas `DataFrame.query` string.
+    ///
+    /// Comparisons, arithmetic, `IN`, `BETWEEN`, `AND`, `OR`, and `NOT` are
+    /// supported. Properties become backtick-quoted column names, and dates
+    /// and timestamps become strings, which pandas compares with datetime
+    /// columns. Spatial, temporal, array, `LIKE`, and `IS NULL` operators
+    /// raise a `ValueError`.
+    fn to_pandas_query(&self) -> PyResult<String> {
+        pandas_query(&self.0)
+    }
+}
+
+/// Writes an expression tree as HTML, coloring each node by its type.
+fn tree_html(tree: &str) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let lines: Vec<String> = tree
+        .lines()
+        .map(|line| {
+            let start = line
+                .find(|c: char| !"│├└─ ".contains(c))
+                .unwrap_or(line.len());
+            let (prefix, node) = line.split_at(start);
+            let (label, type_name) = match node.rsplit_once(": ") {
+                Some((label, type_name)) if TYPE_COLORS.iter().any(|(t, _)| *t == type_name) => {
+                    (label, Some(type_name))
+                }
+                _ => (node, None),
+            };
+            let color = TYPE_COLORS
+                .iter()
+                .find(|(t, _)| Some(*t) == type_name)
+                .map(|(_, color)| *color)
+                .unwrap_or("inherit");
+            let mut html = format!(
+                "<span style=\"color: gray\">{}</span><span style=\"color: {color}\">{}</span>",
+                escape(prefix),
+                escape(label)
+            );
+            if let Some(type_name) = type_name {
+                html.push_str(&format!("<span style=\"color: gray\">: {type_name}</span>"));
+            }
+            html
+        })
+        .collect();
+    format!("<pre>{}</pre>", lines.join("\n"))
+}
+
+/// The color of each type of node in HTML trees.
+const TYPE_COLORS: [(&str, &str); 8] = [
+    ("boolean", "#8250df"),
+    ("number", "#0550ae"),
+    ("string", "#116329"),
+    ("temporal", "#953800"),
+    ("geometry", "#cf222e"),
+    ("array", "#6639ba"),
+    ("object", "#6639ba"),
+    ("null", "#6e7781"),
+];
+
+/// Writes an expression as a pandas query string.
+fn pandas_query(expr: &::cql2::Expr) -> PyResult<String> {
+    use ::cql2::Expr::*;
+    let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+    Ok(match expr {
+        Property { property } => format!("`{}`", property.replace('`', "``")),
+        Float(v) => v.to_string(),
+        Literal(s) => quote(s),
+        Bool(true) => "True".to_string(),
+        Bool(false) => "False".to_string(),
+        Timestamp { timestamp } => pandas_query(timestamp)?,
+        Date { date } => pandas_query(date)?,
+        Array(array) => format!(
+            "[{}]",
+            array
+                .iter()
+                .map(pandas_query)
+                .collect::<PyResult<Vec<_>>>()?
+                .join(", ")
+        ),
+        Operation { op, args } => {
+            let args = args
+                .iter()
+                .map(pandas_query)
+                .collect::<PyResult<Vec<_>>>()?;
+            match (op.as_ref(), args.as_slice()) {
+                ("and", args) => format!("({})", args.join(" and ")),
+                ("or", args) => format!("({})", args.join(" or ")),
+                ("not", [a]) => format!("(not {a})"),
+                ("=", [a, b]) => format!("({a} == {b})"),
+                ("<>", [a, b]) => format!("({a} != {b})"),
+                ("in", [a, b]) => format!("({a} in {b})"),
+                ("between", [a, low, high]) => format!("({low} <= {a} <= {high})"),
+                ("<" | "<=" | ">" | ">=" | "+" | "-" | "*" | "/" | "%", [a, b]) => {
+                    format!("({a} {op} {b})")
+                }
+                ("^", [a, b]) => format!("({a} ** {b})"),
+                ("div", [a, b]) => format!("({a} // {b})"),
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "{op} can't be written as a pandas query"
+                    )))
+                }
+            }
+        }
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "{} can't be written as a pandas query",
+                expr.to_text().unwrap_or_default()
+            )))
+        }
+    })
+}
+
+/// Returns an expression that refers to a property.
+#[pyfunction]
+fn prop(name: String) -> Expr {
+    Expr(::cql2::Expr::Property { property: name })
+}
+
+/// Returns an expression for a value.
+///
+/// Strings are always string literals, never cql2-text, so untrusted input
+/// can't change the structure of an expression. Dictionaries are read as
+/// cql2-json, e.g. GeoJSON geometries or `{"bbox": [...]}`.
+#[pyfunction]
+fn literal(value: Bound<'_, PyAny>) -> Result<Expr> {
+    to_expr(&value).map(Expr)
+}
+
+/// Returns an expression that applies an operator to arguments.
+///
+/// Arguments that aren't already expressions are converted with `literal`.
+#[pyfunction]
+#[pyo3(signature = (op, *args))]
+fn op(op: String, args: Vec<Bound<'_, PyAny>>) -> Result<Expr> {
+    operation(op, &args).map(Expr)
+}
+
+/// Returns the `AND` of one or more expressions.
+#[pyfunction]
+#[pyo3(signature = (*args))]
+fn and_(args: Vec<Bound<'_, PyAny>>) -> Result<Expr> {
+    let args = args.iter().map(to_expr).collect::<Result<Vec<_>>>()?;
+    Ok(Expr(::cql2::Expr::and_all(args)))
+}
+
+/// Returns the `OR` of one or more expressions.
+#[pyfunction]
+#[pyo3(signature = (*args))]
+fn or_(args: Vec<Bound<'_, PyAny>>) -> Result<Expr> {
+    let args = args.iter().map(to_expr).collect::<Result<Vec<_>>>()?;
+    Ok(Expr(::cql2::Expr::or_all(args)))
+}
+
+/// Returns the `NOT` of an expression.
+#[pyfunction]
+fn not_(arg: Bound<'_, PyAny>) -> Result<Expr> {
+    operation("not".to_string(), &[arg]).map(Expr)
+}
+
+/// Returns a timestamp, e.g. `timestamp("2020-01-01T00:00:00Z")`.
+#[pyfunction]
+fn timestamp(value: String) -> Expr {
+    Expr(::cql2::Expr::Timestamp {
+        timestamp: Box::new(::cql2::Expr::Literal(value)),
+    })
+}
+
+/// Returns a date, e.g. `date("2020-01-01")`.
+#[pyfunction]
+fn date(value: String) -> Expr {
+    Expr(::cql2::Expr::Date {
+        date: Box::new(::cql2::Expr::Literal(value)),
+    })
+}
+
+/// Returns an interval between two instants. Use `".."` for an open end.
+#[pyfunction]
+fn interval(start: Bound<'_, PyAny>, end: Bound<'_, PyAny>) -> Result<Expr> {
+    Ok(Expr(::cql2::Expr::Interval {
+        interval: vec![to_expr(&start)?, to_expr(&end)?],
+    }))
+}
+
+macro_rules! binary_ops {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Returns `", stringify!($name), "(a, b)`.")]
+            #[pyfunction]
+            fn $name(a: Bound<'_, PyAny>, b: Bound<'_, PyAny>) -> Result<Expr> {
+                operation(stringify!($name).to_string(), &[a, b]).map(Expr)
+            }
+        )*
+
+        /// Adds the spatial, temporal, and array operator functions to the module.
+        fn add_binary_ops(m: &Bound<'_, PyModule>) -> PyResult<()> {
+            $(m.add_function(wrap_pyfunction!($name, m)?)?;)*
+            Ok(())
+        }
+    };
+}
+
+binary_ops!(
+    s_contains,
+    s_crosses,
+    s_disjoint,
+    s_equals,
+    s_intersects,
+    s_overlaps,
+    s_touches,
+    s_within,
+    t_after,
+    t_before,
+    t_contains,
+    t_disjoint,
+    t_during,
+    t_equals,
+    t_finishedby,
+    t_finishes,
+    t_intersects,
+    t_meets,
+    t_metby,
+    t_overlappedby,
+    t_overlaps,
+    t_startedby,
+    t_starts,
+    a_containedby,
+    a_contains,
+    a_equals,
+    a_overlaps,
+);
+
+/// Converts a Python value to an expression, without copying if it already is
+/// one.
+fn to_expr(value: &Bound<'_, PyAny>) -> Result<::cql2::Expr> {
+    if let Ok(expr) = value.downcast::<Expr>() {
+        Ok(expr.borrow().0.clone())
+    } else {
+        pythonize::depythonize(value).map_err(Error::from)
+    }
+}
+
+fn operation(op: String, args: &[Bound<'_, PyAny>]) -> Result<::cql2::Expr> {
+    Ok(::cql2::Expr::Operation {
+        op: op.into(),
+        args: args.iter().map(to_expr).collect::<Result<_>>()?,
+    })
+}
+
+#[pymethods]
+impl SqlQuery {
+    /// Iterates over the query and the parameters, so a query can be unpacked
+    /// with `query, params = expr.to_sql()`.
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        (self.query.clone(), self.params.clone())
+            .into_pyobject(py)?
+            .try_iter()
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "SqlQuery(query={}, params={})",
+            self.query.clone().into_pyobject(py)?.repr()?,
+            self.params.clone().into_pyobject(py)?.repr()?
+        ))
+    }
+
+    /// Returns the query and parameters as a dictionary with `query` and
+    /// `params` keys.
+    fn asdict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("query", &self.query)?;
+        dict.set_item("params", &self.params)?;
+        Ok(dict)
+    }
 }
 
 impl From<::cql2::SqlQuery> for SqlQuery {
@@ -101,8 +487,11 @@ impl From<Error> for PyErr {
         use ::cql2::Error::*;
         match error {
             Error::Cql2(error) => match error {
-                InvalidCql2Text(..)
+                InvalidBbox(..)
+                | InvalidCql2Text(..)
+                | InvalidTemporalLiteral { .. }
                 | InvalidNumberOfArguments { .. }
+                | MaxDepthExceeded(..)
                 | MissingArgument(..)
                 | ParseBool(..)
                 | ParseFloat(..)
@@ -153,6 +542,16 @@ fn cql2(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(parse_file, m)?)?;
     m.add_function(wrap_pyfunction!(parse_text, m)?)?;
     m.add_function(wrap_pyfunction!(parse_json, m)?)?;
+    m.add_function(wrap_pyfunction!(prop, m)?)?;
+    m.add_function(wrap_pyfunction!(literal, m)?)?;
+    m.add_function(wrap_pyfunction!(op, m)?)?;
+    m.add_function(wrap_pyfunction!(and_, m)?)?;
+    m.add_function(wrap_pyfunction!(or_, m)?)?;
+    m.add_function(wrap_pyfunction!(not_, m)?)?;
+    m.add_function(wrap_pyfunction!(timestamp, m)?)?;
+    m.add_function(wrap_pyfunction!(date, m)?)?;
+    m.add_function(wrap_pyfunction!(interval, m)?)?;
+    add_binary_ops(m)?;
     m.add("ParseError", py.get_type::<ParseError>())?;
     m.add("ValidationError", py.get_type::<ValidationError>())?;
     Ok(())
@@ -1,3 +1,4 @@
+import io
 import json
 from pathlib import Path
 from typing import Any
@@ -15,6 +16,15 @@ def test_parse_file_str(fixtures: Path) -> None:
     cql2.parse_file(str(fixtures / "text" / "example01.txt"))
 
 
+def test_parse_file_object(fixtures: Path, example01_json: dict[str, Any]) -> None:
+    with open(fixtures / "text" / "example01.txt") as f:
+        cql2.parse_file(f)
+    with open(fixtures / "text" / "example01.txt", "rb") as f:
+        cql2.parse_file(f)
+    expr = cql2.parse_file(io.StringIO(json.dumps(example01_json)))
+    assert expr.to_json() == example01_json
+
+
 def test_init(example01_text: str) -> None:
     Expr(example01_text)
 
@@ -46,6 +56,13 @@ def test_to_sql(example01_text: str) -> None:
     sql_query = Expr(example01_text).to_sql()
     assert sql_query.query == '("landsat:scene_id" = $1)'
     assert sql_query.params == ["LC82030282019133LGN00"]
+    query, params = sql_query
+    assert query == sql_query.query
+    assert params == sql_query.params
+    assert sql_query.asdict() == {"query": query, "params": params}
+    assert repr(sql_query) == (
+        "SqlQuery(query='(\"landsat:scene_id\" = $1)', params=['LC82030282019133LGN00'])"
+    )
 
 
 def test_validate() -> None:
@@ -55,5 +72,59 @@ def test_validate() -> None:
             "args": [{"property": "updated_at"}, {"timestamp": "invalid-timestamp"}],
         }
     )
-    with pytest.raises(ValidationError):
+    with pytest.raises(ValidationError) as excinfo:
         expr.validate()
+    assert excinfo.value.json_pointer == excinfo.value.errors[0]["json_pointer"]
+    assert excinfo.value.json_pointer.startswith("/args/1")
+
+
+def test_parse_error_location() -> None:
+    with pytest.raises(ParseError) as excinfo:
+        cql2.parse_text("(foo ~= 'bar')")
+    assert excinfo.value.line == 1
+    assert excinfo.value.column == 6
+    with pytest.raises(ParseError) as excinfo:
+        cql2.parse_json('{"op": "=",\n"args": [}')
+    assert excinfo.value.line == 2
+
+
+def test_to_pandas_query() -> None:
+    expr = Expr("eo:cloud_cover < 10 AND platform IN ('landsat-8', 'landsat-9')")
+    assert (
+        expr.to_pandas_query()
+        == "((`eo:cloud_cover` < 10) and (`platform` in ['landsat-8', 'landsat-9']))"
+    )
+    assert Expr("a div 2 = 1").to_pandas_query() == "((`a` // 2) == 1)"
+    with pytest.raises(ValueError):
+        Expr("s_intersects(geometry, POINT(0 0))").to_pandas_query()
+
+
+def test_builders() -> None:
+    expr = cql2.and_(
+        cql2.op("=", cql2.prop("platform"), "landsat-8' OR 1=1 --"),
+        cql2.s_intersects(
+            cql2.prop("geometry"), {"type": "Point", "coordinates": [-105.1, 40.2]}
+        ),
+        cql2.t_after(cql2.prop("datetime"), cql2.timestamp("2020-01-01T00:00:00Z")),
+    )
+    expr.validate()
+    assert expr.to_json()["args"][0] == {
+        "op": "=",
+        "args": [{"property": "platform"}, "landsat-8' OR 1=1 --"],
+    }
+    assert cql2.not_(cql2.literal(True)).to_text() == "(NOT true)"
+
+
+def test_properties_and_operators() -> None:
+    expr = Expr("eo:cloud_cover < 10 AND s_intersects(geometry, POINT(0 0))")
+    assert expr.properties() == ["eo:cloud_cover", "geometry"]
+    assert expr.operators() == ["and", "<", "s_intersects"]
+
+
+def test_repr() -> None:
+    expr = Expr("a = 'b'")
+    assert repr(expr) == "Expr(\"(a = 'b')\")"
+    assert str(expr) == "(a = 'b')"
+    html = expr._repr_html_()
+    assert html.startswith("<pre>")
+    assert "string" in html
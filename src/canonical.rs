@@ -0,0 +1,160 @@
+use crate::Expr;
+
+/// Operators whose two arguments can be swapped without changing the result.
+const COMMUTATIVE_OPS: [&str; 14] = [
+    "=",
+    "<>",
+    "s_intersects",
+    "s_equals",
+    "s_disjoint",
+    "s_touches",
+    "s_crosses",
+    "s_overlaps",
+    "t_equals",
+    "t_intersects",
+    "t_disjoint",
+    "anyinteracts",
+    "a_equals",
+    "a_overlaps",
+];
+
+impl Expr {
+    /// Returns an equivalent expression in a canonical form, so that
+    /// expressions that differ only in how they were written compare equal.
+    ///
+    /// Canonicalization:
+    ///
+    /// - Flattens nested `AND`s and `OR`s, sorts and de-duplicates their
+    ///   arguments, and drops `true` from `AND` and `false` from `OR`. An
+    ///   `AND` with a `false` argument is `false`, and an `OR` with a `true`
+    ///   argument is `true`.
+    /// - Puts properties on the left of comparisons, e.g. `10 > a` becomes
+    ///   `a < 10`.
+    /// - Sorts the arguments of commutative operators, e.g. `=` and
+    ///   `s_intersects`.
+    /// - Removes double negation.
+    /// - Folds negative numbers, which cql2-text parses as multiplication by
+    ///   `-1`, into a single number.
+    ///
+    /// Arguments are sorted by their cql2-json, so the order is stable but
+    /// not meaningful.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let a: Expr = "b = 2 AND (10 > a OR true) AND b = 2".parse().unwrap();
+    /// let b: Expr = "b = 2".parse().unwrap();
+    /// assert_ne!(a, b);
+    /// assert_eq!(a.canonicalize(), b.canonicalize());
+    /// ```
+    pub fn canonicalize(&self) -> Expr {
+        let Expr::Operation { op, args } = self else {
+            return match self {
+                Expr::Array(array) => Expr::Array(array.iter().map(Expr::canonicalize).collect()),
+                expr => expr.clone(),
+            };
+        };
+        let mut args: Vec<Expr> = args.iter().map(|arg| arg.canonicalize()).collect();
+        match op.as_ref() {
+            "and" | "or" => {
+                // `false` for AND, `true` for OR.
+                let absorbing = Expr::Bool(op == "or");
+                if args.contains(&absorbing) {
+                    return absorbing;
+                }
+                let combined = if op == "and" {
+                    Expr::and_all(args)
+                } else {
+                    Expr::or_all(args)
+                };
+                let Expr::Operation { op, args } = combined else {
+                    return combined;
+                };
+                let mut args = args;
+                args.sort_by_cached_key(sort_key);
+                args.dedup();
+                if args.len() == 1 {
+                    return args.remove(0);
+                }
+                return Expr::Operation { op, args };
+            }
+            "not" if args.len() == 1 => {
+                if let Expr::Operation { op, args: inner } = &args[0] {
+                    if op == "not" && inner.len() == 1 {
+                        return inner[0].clone();
+                    }
+                }
+            }
+            "*" if args.len() == 2 && args[0] == Expr::Float(-1.0) => {
+                if let Expr::Float(v) = args[1] {
+                    return Expr::Float(-v);
+                }
+            }
+            _ => {}
+        }
+        let mut op = op.clone();
+        if args.len() == 2 {
+            let is_property = |expr: &Expr| matches!(expr, Expr::Property { .. });
+            let mirrored = match op.as_ref() {
+                "<" => Some(">"),
+                "<=" => Some(">="),
+                ">" => Some("<"),
+                ">=" => Some("<="),
+                _ => None,
+            };
+            if let Some(mirrored) = mirrored {
+                if is_property(&args[1]) && !is_property(&args[0]) {
+                    args.swap(0, 1);
+                    op = mirrored.into();
+                }
+            } else if COMMUTATIVE_OPS.contains(&op.as_ref()) {
+                if is_property(&args[1]) && !is_property(&args[0]) {
+                    args.swap(0, 1);
+                } else if is_property(&args[0]) == is_property(&args[1]) {
+                    args.sort_by_cached_key(sort_key);
+                }
+            }
+        }
+        Expr::Operation { op, args }
+    }
+}
+
+fn sort_key(expr: &Expr) -> String {
+    expr.to_json().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expr;
+
+    fn canonical_text(s: &str) -> String {
+        let expr: Expr = s.parse().unwrap();
+        expr.canonicalize().to_text().unwrap()
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(canonical_text("-10 >= a"), "(a <= -10)");
+        assert_eq!(canonical_text("'x' = a"), "(a = 'x')");
+        assert_eq!(canonical_text("b = a"), canonical_text("a = b"));
+    }
+
+    #[test]
+    fn boolean() {
+        assert_eq!(
+            canonical_text("NOT (NOT (a = 1)) OR a = 1 OR (b = 2 OR c = 3)"),
+            canonical_text("c = 3 OR b = 2 OR a = 1")
+        );
+        assert_eq!(canonical_text("a = 1 AND false"), "false");
+    }
+
+    #[test]
+    fn spatial() {
+        assert_eq!(
+            canonical_text("s_intersects(POINT(0 0), geometry)"),
+            "s_intersects(geometry, POINT(0 0))"
+        );
+    }
+}
@@ -1,9 +1,30 @@
+use pest::error::{InputLocation, LineColLocation};
+use std::ops::Range;
 use thiserror::Error;
 
 /// Crate-specific error enum.
 #[derive(Debug, Error)]
 #[allow(clippy::large_enum_variant)]
 pub enum Error {
+    /// An [OperatorPlugin](crate::OperatorPlugin) is named after a built-in
+    /// operator.
+    #[error("{0} is a built-in operator and can't be replaced by a plugin")]
+    BuiltinOperator(String),
+
+    /// [ciborium::de::Error]
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    CborDeserialize(#[from] ciborium::de::Error<std::io::Error>),
+
+    /// [ciborium::ser::Error]
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    CborSerialize(#[from] ciborium::ser::Error<std::io::Error>),
+
+    /// [std::fmt::Error]
+    #[error(transparent)]
+    Fmt(#[from] std::fmt::Error),
+
     /// [geojson::Error]
     #[error(transparent)]
     GeoJSON(#[from] geojson::Error),
@@ -12,10 +33,37 @@ pub enum Error {
     #[error(transparent)]
     Geozero(#[from] geozero::error::GeozeroError),
 
+    /// Invalid bbox
+    #[error("invalid bbox: {0}")]
+    InvalidBbox(String),
+
     /// Invalid CQL2 text
     #[error("invalid cql2-text: {0}")]
     InvalidCql2Text(String),
 
+    /// A filter that doesn't pass validation, see [FilterParams::to_expr](crate::FilterParams::to_expr).
+    #[error("invalid filter: {0}")]
+    InvalidFilter(crate::ValidationReport),
+
+    /// A JSON Schema that can't be compiled, see [Validator::from_schema](crate::Validator::from_schema).
+    #[error("invalid schema: {0}")]
+    InvalidSchema(String),
+
+    /// A date or timestamp literal that isn't a full, valid RFC 3339 date or
+    /// timestamp.
+    #[error("invalid temporal literal '{literal}' at '{pointer}': {reason}")]
+    InvalidTemporalLiteral {
+        /// The literal
+        literal: String,
+
+        /// A JSON pointer to the literal in the cql2-json representation of
+        /// the expression
+        pointer: String,
+
+        /// Why the literal is invalid
+        reason: String,
+    },
+
     /// Invalid number of arguments for the expression
     #[error("invalid number of arguments for {name}: {actual} (expected {expected})")]
     InvalidNumberOfArguments {
@@ -33,10 +81,18 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    /// An expression is nested more deeply than allowed.
+    #[error("expression is nested more than {0} levels deep")]
+    MaxDepthExceeded(usize),
+
     /// Missing argument from a function that requires one.
     #[error("function {0} is missing a required argument")]
     MissingArgument(&'static str),
 
+    /// [jiff::Error]
+    #[error(transparent)]
+    Jiff(#[from] jiff::Error),
+
     /// [std::str::ParseBoolError]
     #[error(transparent)]
     ParseBool(#[from] std::str::ParseBoolError),
@@ -57,6 +113,18 @@ pub enum Error {
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
 
+    /// A `filter-lang` other than `cql2-text` or `cql2-json`.
+    #[error("unknown filter-lang: {0}")]
+    UnknownFilterLang(String),
+
+    /// A property that isn't in the queryables used to write SQL.
+    #[error("unknown property: {0}")]
+    UnknownProperty(String),
+
+    /// A `filter-crs` other than [CRS84](crate::CRS84).
+    #[error("unsupported filter-crs: {0}")]
+    UnsupportedFilterCrs(String),
+
     /// A validation error.
     ///
     /// This holds a [serde_json::Value] that is the output from a
@@ -66,3 +134,63 @@ pub enum Error {
     #[error("validation error")]
     Validation(serde_json::Value),
 }
+
+impl Error {
+    /// Returns the byte range of the cql2-text input where parsing failed, for
+    /// parsing errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let error = cql2::parse_text("(foo ~= 'bar')").unwrap_err();
+    /// assert_eq!(error.span(), Some(5..5));
+    /// ```
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Error::Pest(error) => Some(match error.location {
+                InputLocation::Pos(pos) => pos..pos,
+                InputLocation::Span((start, end)) => start..end,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the one-based line and column where parsing failed, for
+    /// cql2-text and cql2-json parsing errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let error = cql2::parse_text("(foo ~= 'bar')").unwrap_err();
+    /// assert_eq!(error.line_column(), Some((1, 6)));
+    /// ```
+    pub fn line_column(&self) -> Option<(usize, usize)> {
+        match self {
+            Error::Pest(error) => Some(match error.line_col {
+                LineColLocation::Pos(pos) => pos,
+                LineColLocation::Span(start, _) => start,
+            }),
+            Error::SerdeJson(error) if error.line() > 0 => Some((error.line(), error.column())),
+            _ => None,
+        }
+    }
+
+    /// Returns a JSON pointer to the part of the expression that caused the
+    /// error, for errors that have one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "t_after(datetime, DATE('2021-02-29'))".parse().unwrap();
+    /// let error = expr.check_temporal_literals().unwrap_err();
+    /// assert_eq!(error.pointer(), Some("/args/1/date"));
+    /// ```
+    pub fn pointer(&self) -> Option<&str> {
+        match self {
+            Error::InvalidTemporalLiteral { pointer, .. } => Some(pointer),
+            _ => None,
+        }
+    }
+}
@@ -1,8 +1,19 @@
-use crate::{Error, Geometry, SqlQuery, Validator};
+use crate::{
+    jsonb::type_hint, temporal::range_operator, Error, Geometry, GeometryFormat, Queryables,
+    SqlQuery, TemporalFormat, ToSqlOptions, Type, Validator, DEFAULT_MAX_DEPTH,
+};
+use geo_types::{coord, Rect};
 use pg_escape::{quote_identifier, quote_literal};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::str::FromStr;
+use std::{
+    borrow::Cow,
+    fmt,
+    hash::{Hash, Hasher},
+    io,
+    ops::{BitAnd, BitOr, Not},
+    str::FromStr,
+};
 
 /// A CQL2 expression.
 ///
@@ -18,20 +29,57 @@ use std::str::FromStr;
 ///
 /// Use [Expr::to_text], [Expr::to_json], and [Expr::to_sql] to use the CQL2,
 /// and use [Expr::is_valid] to check validity.
+///
+/// Expressions can be combined with `&` (AND), `|` (OR), and `!` (NOT):
+///
+/// ```
+/// use cql2::Expr;
+///
+/// let a: Expr = "foo = 1".parse().unwrap();
+/// let b: Expr = "bar = 2".parse().unwrap();
+/// let c: Expr = "baz = 3".parse().unwrap();
+/// let expr = a & (b | !c);
+/// assert_eq!(expr.to_text().unwrap(), "((foo = 1) AND ((bar = 2) OR (NOT (baz = 3))))");
+/// ```
+///
+/// [Expr] implements [Eq] and [Hash], so expressions can be used as keys in
+/// maps. Equality is structural: numbers, including GeoJSON coordinates, are
+/// compared by their bit patterns, so `NaN` equals itself. `-0.0` is compared
+/// as `0.0`, because every output writes it as `0`. A WKT geometry never
+/// equals a GeoJSON one, even if they describe the same shape.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 #[allow(missing_docs)]
 pub enum Expr {
-    Operation { op: String, args: Vec<Box<Expr>> },
-    Interval { interval: Vec<Box<Expr>> },
-    Timestamp { timestamp: Box<Expr> },
-    Date { date: Box<Expr> },
-    Property { property: String },
-    BBox { bbox: Vec<Box<Expr>> },
+    Operation {
+        #[serde(deserialize_with = "deserialize_op")]
+        op: Cow<'static, str>,
+        args: Vec<Expr>,
+    },
+    Interval {
+        interval: Vec<Expr>,
+    },
+    Timestamp {
+        timestamp: Box<Expr>,
+    },
+    Date {
+        date: Box<Expr>,
+    },
+    Duration {
+        duration: Box<Expr>,
+    },
+    Property {
+        property: String,
+    },
+    BBox {
+        bbox: Vec<Expr>,
+    },
+    #[serde(serialize_with = "serialize_number")]
     Float(f64),
     Literal(String),
     Bool(bool),
-    Array(Vec<Box<Expr>>),
+    Null,
+    Array(Vec<Expr>),
     Geometry(Geometry),
 }
 
@@ -47,82 +95,241 @@ impl Expr {
     /// assert_eq!(expr.to_text().unwrap(), "true");
     /// ```
     pub fn to_text(&self) -> Result<String, Error> {
-        macro_rules! check_len {
-            ($name:expr, $args:expr, $len:expr, $text:expr) => {
-                if $args.len() == $len {
-                    Ok($text)
-                } else {
-                    Err(Error::InvalidNumberOfArguments {
-                        name: $name.to_string(),
-                        actual: $args.len(),
-                        expected: $len,
-                    })
+        self.check_depth(DEFAULT_MAX_DEPTH)?;
+        let mut s = String::new();
+        self.write_text_inner(&mut s, None)?;
+        Ok(s)
+    }
+
+    /// Converts this expression to CQL2 text, rounding geometry coordinates
+    /// to at most `precision` decimal places.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "s_intersects(geometry, POINT(-105.10193 40.16721))".parse().unwrap();
+    /// assert_eq!(
+    ///     expr.to_text_with_precision(2).unwrap(),
+    ///     "s_intersects(geometry, POINT(-105.1 40.17))"
+    /// );
+    /// ```
+    pub fn to_text_with_precision(&self, precision: usize) -> Result<String, Error> {
+        self.check_depth(DEFAULT_MAX_DEPTH)?;
+        let mut s = String::new();
+        self.write_text_inner(&mut s, Some(precision))?;
+        Ok(s)
+    }
+
+    /// Writes this expression as CQL2 text, without building intermediate
+    /// strings for its arguments.
+    ///
+    /// [Expr] also implements [Display](std::fmt::Display), which writes the
+    /// same text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "foo = 1 AND bar = 2".parse().unwrap();
+    /// let mut s = String::new();
+    /// expr.write_text(&mut s).unwrap();
+    /// assert_eq!(s, "((foo = 1) AND (bar = 2))");
+    /// assert_eq!(expr.to_string(), s);
+    /// ```
+    pub fn write_text(&self, w: &mut impl fmt::Write) -> Result<(), Error> {
+        self.check_depth(DEFAULT_MAX_DEPTH)?;
+        self.write_text_inner(w, None)
+    }
+
+    fn write_text_inner(
+        &self,
+        w: &mut impl fmt::Write,
+        precision: Option<usize>,
+    ) -> Result<(), Error> {
+        fn check_len(name: &str, args: &[Expr], len: usize) -> Result<(), Error> {
+            if args.len() == len {
+                Ok(())
+            } else {
+                Err(Error::InvalidNumberOfArguments {
+                    name: name.to_string(),
+                    actual: args.len(),
+                    expected: len,
+                })
+            }
+        }
+
+        fn write_list(
+            w: &mut impl fmt::Write,
+            exprs: &[Expr],
+            separator: &str,
+            precision: Option<usize>,
+        ) -> Result<(), Error> {
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    w.write_str(separator)?;
                 }
-            };
+                expr.write_text_inner(w, precision)?;
+            }
+            Ok(())
         }
 
         match self {
-            Expr::Bool(v) => Ok(v.to_string()),
-            Expr::Float(v) => Ok(v.to_string()),
-            Expr::Literal(v) => Ok(quote_literal(v).to_string()),
-            Expr::Property { property } => Ok(quote_identifier(property).to_string()),
+            Expr::Bool(v) => write!(w, "{v}")?,
+            Expr::Null => w.write_str("NULL")?,
+            Expr::Float(v) => w.write_str(&format_number(*v))?,
+            Expr::Literal(v) => w.write_str(&quote_literal(v))?,
+            Expr::Property { property } => w.write_str(&quote_identifier(property))?,
             Expr::Interval { interval } => {
-                check_len!(
-                    "interval",
-                    interval,
-                    2,
-                    format!(
-                        "INTERVAL({},{})",
-                        interval[0].to_text()?,
-                        interval[1].to_text()?
-                    )
-                )
+                check_len("interval", interval, 2)?;
+                w.write_str("INTERVAL(")?;
+                write_list(w, interval, ",", precision)?;
+                w.write_char(')')?;
+            }
+            Expr::Date { date } => {
+                w.write_str("DATE(")?;
+                date.write_text_inner(w, precision)?;
+                w.write_char(')')?;
+            }
+            Expr::Duration { duration } => {
+                w.write_str("DURATION(")?;
+                duration.write_text_inner(w, precision)?;
+                w.write_char(')')?;
+            }
+            Expr::Timestamp { timestamp } => {
+                w.write_str("TIMESTAMP(")?;
+                timestamp.write_text_inner(w, precision)?;
+                w.write_char(')')?;
             }
-            Expr::Date { date } => Ok(format!("DATE({})", date.to_text()?)),
-            Expr::Timestamp { timestamp } => Ok(format!("TIMESTAMP({})", timestamp.to_text()?)),
-            Expr::Geometry(v) => v.to_wkt(),
+            Expr::Geometry(v) => w.write_str(&match precision {
+                Some(precision) => v.to_wkt_with_precision(precision)?.into(),
+                None => v.wkt()?,
+            })?,
             Expr::Array(v) => {
-                let array_els: Vec<String> =
-                    v.iter().map(|a| a.to_text()).collect::<Result<_, _>>()?;
-                Ok(format!("({})", array_els.join(", ")))
+                w.write_char('(')?;
+                write_list(w, v, ", ", precision)?;
+                w.write_char(')')?;
             }
-            Expr::Operation { op, args } => {
-                let a: Vec<String> = args.iter().map(|x| x.to_text()).collect::<Result<_, _>>()?;
-                match op.as_str() {
-                    "and" => Ok(format!("({})", a.join(" AND "))),
-                    "or" => Ok(format!("({})", a.join(" OR "))),
-                    "like" => Ok(format!("({} LIKE {})", a[0], a[1])),
-                    "in" => Ok(format!("({} IN {})", a[0], a[1])),
-                    "between" => {
-                        check_len!(
-                            "between",
-                            a,
-                            3,
-                            format!("({} BETWEEN {} AND {})", a[0], a[1], a[2])
-                        )
-                    }
-                    "not" => {
-                        check_len!("not", a, 1, format!("(NOT {})", a[0]))
-                    }
-                    "isNull" => {
-                        check_len!("is null", a, 1, format!("({} IS NULL)", a[0]))
-                    }
-                    "+" | "-" | "*" | "/" | "%" => {
-                        let paddedop = format!(" {} ", op);
-                        Ok(a.join(&paddedop).to_string())
-                    }
-                    "^" | "=" | "<=" | "<" | "<>" | ">" | ">=" => {
-                        check_len!(op, a, 2, format!("({} {} {})", a[0], op, a[1]))
+            Expr::Operation { op, args } => match op.as_ref() {
+                "and" | "or" => {
+                    w.write_char('(')?;
+                    write_list(
+                        w,
+                        args,
+                        if op == "and" { " AND " } else { " OR " },
+                        precision,
+                    )?;
+                    w.write_char(')')?;
+                }
+                "not" | "isNull" => {
+                    check_len(if op == "not" { "not" } else { "is null" }, args, 1)?;
+                    if op == "not" {
+                        w.write_str("(NOT ")?;
+                        args[0].write_text_inner(w, precision)?;
+                    } else {
+                        w.write_char('(')?;
+                        args[0].write_text_inner(w, precision)?;
+                        w.write_str(" IS NULL")?;
                     }
-                    _ => Ok(format!("{}({})", quote_identifier(op), a.join(", "))),
+                    w.write_char(')')?;
                 }
-            }
+                "between" => {
+                    check_len("between", args, 3)?;
+                    w.write_char('(')?;
+                    args[0].write_text_inner(w, precision)?;
+                    w.write_str(" BETWEEN ")?;
+                    write_list(w, &args[1..], " AND ", precision)?;
+                    w.write_char(')')?;
+                }
+                "like" | "in" | "^" | "=" | "<=" | "<" | "<>" | ">" | ">=" => {
+                    check_len(op, args, 2)?;
+                    let keyword = match op.as_ref() {
+                        "like" => "LIKE",
+                        "in" => "IN",
+                        op => op,
+                    };
+                    w.write_char('(')?;
+                    args[0].write_text_inner(w, precision)?;
+                    write!(w, " {keyword} ")?;
+                    args[1].write_text_inner(w, precision)?;
+                    w.write_char(')')?;
+                }
+                "+" | "-" | "*" | "/" | "%" | "div" => {
+                    write_list(w, args, &format!(" {op} "), precision)?;
+                }
+                _ => {
+                    write!(w, "{}(", quote_identifier(op))?;
+                    write_list(w, args, ", ", precision)?;
+                    w.write_char(')')?;
+                }
+            },
             Expr::BBox { bbox } => {
-                let array_els: Vec<String> =
-                    bbox.iter().map(|a| a.to_text()).collect::<Result<_, _>>()?;
-                Ok(format!("BBOX({})", array_els.join(", ")))
+                w.write_str("BBOX(")?;
+                write_list(w, bbox, ", ", precision)?;
+                w.write_char(')')?;
             }
         }
+        Ok(())
+    }
+
+    /// Converts this expression to CQL2 text, spread over multiple lines.
+    ///
+    /// Each argument of an AND or OR is put on its own line, indented by its
+    /// nesting depth. Everything else is formatted as in [Expr::to_text].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "foo = 1 AND (bar = 2 OR baz = 3)".parse().unwrap();
+    /// assert_eq!(
+    ///     expr.to_text_pretty().unwrap(),
+    ///     "(
+    ///     (foo = 1)
+    ///     AND (
+    ///         (bar = 2)
+    ///         OR (baz = 3)
+    ///     )
+    /// )"
+    /// );
+    /// ```
+    pub fn to_text_pretty(&self) -> Result<String, Error> {
+        self.check_depth(DEFAULT_MAX_DEPTH)?;
+        self.to_text_pretty_inner(0)
+    }
+
+    fn to_text_pretty_inner(&self, depth: usize) -> Result<String, Error> {
+        const INDENT: &str = "    ";
+        match self {
+            Expr::Operation { op, args } if op == "and" || op == "or" => {
+                let indent = INDENT.repeat(depth + 1);
+                let keyword = op.to_uppercase();
+                let lines = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        let text = arg.to_text_pretty_inner(depth + 1)?;
+                        if i == 0 {
+                            Ok(format!("{indent}{text}"))
+                        } else {
+                            Ok(format!("{indent}{keyword} {text}"))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(format!(
+                    "(\n{}\n{})",
+                    lines.join("\n"),
+                    INDENT.repeat(depth)
+                ))
+            }
+            Expr::Operation { op, args } if op == "not" && args.len() == 1 => {
+                Ok(format!("(NOT {})", args[0].to_text_pretty_inner(depth)?))
+            }
+            _ => self.to_text(),
+        }
     }
 
     /// Converts this expression to a [SqlQuery] struct with parameters
@@ -137,77 +344,193 @@ impl Expr {
     /// let s = expr.to_sql().unwrap();
     /// ```
     pub fn to_sql(&self) -> Result<SqlQuery, Error> {
+        self.to_sql_with_options(&ToSqlOptions::default())
+    }
+
+    /// Converts this expression to a [SqlQuery] struct, configured with
+    /// [ToSqlOptions].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, ToSqlOptions};
+    ///
+    /// let expr: Expr = "s_intersects(geometry, POINT(-105.10193 40.16721))".parse().unwrap();
+    /// let options = ToSqlOptions {
+    ///     precision: Some(2),
+    ///     ..Default::default()
+    /// };
+    /// let sql = expr.to_sql_with_options(&options).unwrap();
+    /// assert_eq!(sql.params, ["EPSG:4326;POINT(-105.1 40.17)"]);
+    /// ```
+    pub fn to_sql_with_options(&self, options: &ToSqlOptions) -> Result<SqlQuery, Error> {
+        self.check_depth(options.max_depth)?;
         let params: &mut Vec<String> = &mut vec![];
-        let query = self.to_sql_inner(params)?;
+        let query = self.to_sql_inner(params, options)?;
         Ok(SqlQuery {
             query,
             params: params.to_vec(),
         })
     }
 
-    fn to_sql_inner(&self, params: &mut Vec<String>) -> Result<String, Error> {
+    pub(crate) fn to_sql_inner(
+        &self,
+        params: &mut Vec<String>,
+        options: &ToSqlOptions,
+    ) -> Result<String, Error> {
         Ok(match self {
-            Expr::Bool(v) => {
-                params.push(v.to_string());
-                format!("${}", params.len())
-            }
-            Expr::Float(v) => {
-                params.push(v.to_string());
-                format!("${}", params.len())
+            Expr::Bool(v) => param(params, options, v.to_string(), false),
+            Expr::Null => "NULL".to_string(),
+            // Non-finite numbers are only valid SQL as quoted strings, e.g. 'inf'.
+            Expr::Float(v) => param(params, options, format_number(*v), !v.is_finite()),
+            Expr::Literal(v) => param(params, options, v.to_string(), true),
+            Expr::Date { date } => format!("{}::date", date.to_sql_inner(params, options)?),
+            Expr::Duration { duration } => {
+                format!("{}::interval", duration.to_sql_inner(params, options)?)
             }
-            Expr::Literal(v) => {
-                params.push(v.to_string());
-                format!("${}", params.len())
+            Expr::Timestamp { timestamp } => {
+                format!("{}::timestamptz", timestamp.to_sql_inner(params, options)?)
             }
-            Expr::Date { date } => date.to_sql_inner(params)?,
-            Expr::Timestamp { timestamp } => timestamp.to_sql_inner(params)?,
 
             Expr::Interval { interval } => {
+                // Open bounds are NULL, which TSTZRANGE treats as unbounded.
                 let a: Vec<String> = interval
                     .iter()
-                    .map(|x| x.to_sql_inner(params))
+                    .map(|x| match x {
+                        Expr::Literal(v) if v == ".." => Ok("NULL".to_string()),
+                        x => x.to_sql_inner(params, options),
+                    })
                     .collect::<Result<_, _>>()?;
+                if a.len() != 2 {
+                    return Err(Error::InvalidNumberOfArguments {
+                        name: "interval".to_string(),
+                        actual: a.len(),
+                        expected: 2,
+                    });
+                }
                 format!("TSTZRANGE({},{})", a[0], a[1],)
             }
             Expr::Geometry(v) => {
-                params.push(format!("EPSG:4326;{}", v.to_wkt()?));
-                format!("${}", params.len())
+                let wkt = match options.precision {
+                    Some(precision) => v.to_wkt_with_precision(precision)?,
+                    None => v.to_wkt()?,
+                };
+                let srid = options.srid;
+                let value = match options.geometry_format {
+                    GeometryFormat::EpsgWkt => format!("EPSG:{srid};{wkt}"),
+                    GeometryFormat::Ewkt => format!("SRID={srid};{wkt}"),
+                    GeometryFormat::Wkt | GeometryFormat::SetSrid => wkt,
+                };
+                let value = param(params, options, value, true);
+                if options.geometry_format == GeometryFormat::SetSrid {
+                    format!("ST_SetSRID(ST_GeomFromText({value}), {srid})")
+                } else {
+                    value
+                }
             }
             Expr::Array(v) => {
                 let array_els: Vec<String> = v
                     .iter()
-                    .map(|a| a.to_sql_inner(params))
+                    .map(|a| a.to_sql_inner(params, options))
                     .collect::<Result<_, _>>()?;
                 format!("[{}]", array_els.join(", "))
             }
-            Expr::Property { property } => format!("\"{property}\""),
+            Expr::Property { property } => property_sql(property, None, options)?,
+            Expr::Operation { op, args }
+                if options.temporal_format == TemporalFormat::Range
+                    && args.len() == 2
+                    && range_operator(op).is_some() =>
+            {
+                let range_op = range_operator(op).expect("checked in the match guard");
+                let a = args[0].to_sql_range(params, options)?;
+                let b = args[1].to_sql_range(params, options)?;
+                if op == "t_disjoint" {
+                    format!("(NOT ({a} {range_op} {b}))")
+                } else {
+                    format!("({a} {range_op} {b})")
+                }
+            }
+            Expr::Operation { op, args } if op == "like" && args.len() == 2 => {
+                // CQL2 escapes wildcards with a backslash. `CASEI` on both
+                // sides is a case-insensitive match.
+                let (keyword, a, b) = match (args[0].casei_arg(), args[1].casei_arg()) {
+                    (Some(a), Some(b)) => ("ILIKE", a, b),
+                    _ => ("LIKE", &args[0], &args[1]),
+                };
+                format!(
+                    "({} {keyword} {} ESCAPE '\\')",
+                    a.to_sql_inner(params, options)?,
+                    b.to_sql_inner(params, options)?
+                )
+            }
             Expr::Operation { op, args } => {
+                let hint = match (&options.jsonb, &options.queryables) {
+                    (None, _) => None,
+                    (Some(_), Some(queryables)) => type_hint(op, args, queryables),
+                    (Some(_), None) => type_hint(op, args, &Queryables::default()),
+                };
                 let a: Vec<String> = args
                     .iter()
-                    .map(|x| x.to_sql_inner(params))
+                    .map(|x| x.to_sql_typed(params, options, hint))
                     .collect::<Result<_, _>>()?;
-                match op.as_str() {
+                if let Some(sql) = options.operators.to_sql(op, &a) {
+                    return sql;
+                }
+                if let Some(expected) = sql_arity(op).filter(|expected| *expected != a.len()) {
+                    return Err(Error::InvalidNumberOfArguments {
+                        name: op.to_string(),
+                        actual: a.len(),
+                        expected,
+                    });
+                }
+                match op.as_ref() {
                     "and" => format!("({})", a.join(" AND ")),
                     "or" => format!("({})", a.join(" OR ")),
                     "between" => format!("({} BETWEEN {} AND {})", a[0], a[1], a[2]),
                     "not" => format!("(NOT {})", a[0]),
-                    "is null" => format!("({} IS NULL)", a[0]),
+                    "isNull" => format!("({} IS NULL)", a[0]),
+                    "+" | "-"
+                        if args.len() == 2
+                            && args[0].is_now()
+                            && matches!(args[1], Expr::Literal(_)) =>
+                    {
+                        // The literal is an ISO 8601 duration, e.g. `now() - 'P7D'`.
+                        format!("({} {} {}::interval)", a[0], op, a[1])
+                    }
                     "+" | "-" | "*" | "/" | "%" | "^" | "=" | "<=" | "<" | "<>" | ">" | ">=" => {
                         format!("({} {} {})", a[0], op, a[1])
                     }
-                    _ => format!("{}({})", op, a.join(", ")),
+                    // Integer division, rounded down.
+                    "div" => format!("floor({} / {})", a[0], a[1]),
+                    op if is_known_op(op) => format!("{}({})", op, a.join(", ")),
+                    // Quoted so that PostgreSQL doesn't fold it to lowercase.
+                    op => format!("{}({})", quote_identifier(op), a.join(", ")),
                 }
             }
             Expr::BBox { bbox } => {
                 let array_els: Vec<String> = bbox
                     .iter()
-                    .map(|a| a.to_sql_inner(params))
+                    .map(|a| a.to_sql_inner(params, options))
                     .collect::<Result<_, _>>()?;
                 format!("[{}]", array_els.join(", "))
             }
         })
     }
 
+    /// Converts this expression to SQL, casting properties that are JSONB
+    /// accessors to `hint` if they don't have a queryable type.
+    pub(crate) fn to_sql_typed(
+        &self,
+        params: &mut Vec<String>,
+        options: &ToSqlOptions,
+        hint: Option<Type>,
+    ) -> Result<String, Error> {
+        match self {
+            Expr::Property { property } => property_sql(property, hint, options),
+            expr => expr.to_sql_inner(params, options),
+        }
+    }
+
     /// Converts this expression to a JSON string.
     ///
     /// # Examples
@@ -236,6 +559,23 @@ impl Expr {
         serde_json::to_string_pretty(&self).map_err(Error::from)
     }
 
+    /// Writes this expression as cql2-json to a writer, without building an
+    /// intermediate string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "foo = 1".parse().unwrap();
+    /// let mut buf = Vec::new();
+    /// expr.write_json(&mut buf).unwrap();
+    /// assert_eq!(buf, expr.to_json().unwrap().as_bytes());
+    /// ```
+    pub fn write_json(&self, w: impl io::Write) -> Result<(), Error> {
+        serde_json::to_writer(w, self).map_err(Error::from)
+    }
+
     /// Converts this expression to a [serde_json::Value].
     ///
     /// # Examples
@@ -250,8 +590,31 @@ impl Expr {
         serde_json::to_value(self).map_err(Error::from)
     }
 
+    /// Converts this expression to CBOR, a compact binary encoding of cql2-json.
+    ///
+    /// Use [crate::parse_cbor] to read the expression back. Requires the `cbor`
+    /// feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr = Expr::Bool(true);
+    /// let bytes = expr.to_cbor().unwrap();
+    /// ```
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
     /// Returns true if this expression is valid CQL2.
     ///
+    /// The expression must match the CQL2 JSON Schema and all of its date and
+    /// timestamp literals must pass [Expr::check_temporal_literals].
+    ///
     /// For detailed error reporting, use [Validator::validate] in conjunction with [Expr::to_value].
     ///
     /// # Examples
@@ -267,17 +630,638 @@ impl Expr {
     ///
     /// Panics if the default validator can't be created.
     pub fn is_valid(&self) -> bool {
-        let value = serde_json::to_value(self);
-        match &value {
-            Ok(value) => {
-                let validator = Validator::new().expect("Could not create default validator");
-                validator.validate(value).is_ok()
+        Validator::default_ref().validate_schema(self).is_valid()
+            && self.check_temporal_literals().is_ok()
+    }
+
+    /// Returns the two-dimensional bounding box of this expression.
+    ///
+    /// Bounding boxes are returned as-is (three-dimensional bounding boxes are
+    /// flattened) and geometries return their envelope. Bounding boxes that
+    /// cross the antimeridian, i.e. with `xmin > xmax`, can't be represented
+    /// as a single [Rect], so they return `None`, like all other expressions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "BBOX(-105, 40, -104, 41)".parse().unwrap();
+    /// let rect = expr.bounding_box().unwrap().unwrap();
+    /// assert_eq!(rect.min().x, -105.);
+    ///
+    /// let expr: Expr = "POINT(-105.1 40.2)".parse().unwrap();
+    /// let rect = expr.bounding_box().unwrap().unwrap();
+    /// assert_eq!(rect.min(), rect.max());
+    ///
+    /// let expr: Expr = "foo = 42".parse().unwrap();
+    /// assert!(expr.bounding_box().unwrap().is_none());
+    /// ```
+    pub fn bounding_box(&self) -> Result<Option<Rect>, Error> {
+        match self {
+            Expr::BBox { bbox } => bbox_to_rect(bbox),
+            Expr::Operation { op, args } if op == "bbox" => bbox_to_rect(args),
+            Expr::Geometry(geometry) => geometry.bounding_box(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the names of the properties in this expression, in the order
+    /// they first appear, without duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 10 AND (platform = 'landsat-8' OR eo:cloud_cover IS NULL)"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(expr.properties(), ["eo:cloud_cover", "platform"]);
+    /// ```
+    pub fn properties(&self) -> Vec<&str> {
+        let mut properties = Vec::new();
+        self.walk(&mut |expr| {
+            if let Expr::Property { property } = expr {
+                if !properties.contains(&property.as_str()) {
+                    properties.push(property.as_str());
+                }
+            }
+        });
+        properties
+    }
+
+    /// Returns the operators and functions in this expression, in the order
+    /// they first appear, without duplicates.
+    ///
+    /// Negative numbers, which cql2-text parses as multiplication by -1, don't
+    /// count as uses of `*`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "s_intersects(geometry, POINT(0 0)) AND CASEI(platform) = CASEI('landsat-8') AND gsd > -1"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(expr.operators(), ["and", "s_intersects", "=", "casei", ">"]);
+    /// ```
+    pub fn operators(&self) -> Vec<&str> {
+        let mut operators = Vec::new();
+        self.walk(&mut |expr| {
+            if let Expr::Operation { op, args } = expr {
+                let is_negative_number = op == "*"
+                    && args.len() == 2
+                    && args[0] == Expr::Float(-1.0)
+                    && matches!(args[1], Expr::Float(_));
+                if !is_negative_number && !operators.contains(&op.as_ref()) {
+                    operators.push(op.as_ref());
+                }
+            }
+        });
+        operators
+    }
+
+    /// Returns how deeply this expression is nested, counting each operation,
+    /// array, interval, bounding box, and temporal literal as a level.
+    ///
+    /// A single value has a depth of one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// assert_eq!(Expr::Bool(true).depth(), 1);
+    /// let expr: Expr = "a = 1 OR NOT b = 2".parse().unwrap();
+    /// assert_eq!(expr.depth(), 4);
+    /// ```
+    pub fn depth(&self) -> usize {
+        let mut max = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((expr, depth)) = stack.pop() {
+            max = max.max(depth);
+            stack.extend(expr.children().iter().map(|child| (child, depth + 1)));
+        }
+        max
+    }
+
+    /// Returns an error if this expression is nested more than `max_depth`
+    /// levels deep, see [Expr::depth].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "a = 1 OR NOT b = 2".parse().unwrap();
+    /// assert!(expr.check_depth(4).is_ok());
+    /// assert!(expr.check_depth(3).is_err());
+    /// ```
+    pub fn check_depth(&self, max_depth: usize) -> Result<(), Error> {
+        let mut stack = vec![(self, 1)];
+        while let Some((expr, depth)) = stack.pop() {
+            if depth > max_depth {
+                return Err(Error::MaxDepthExceeded(max_depth));
+            }
+            stack.extend(expr.children().iter().map(|child| (child, depth + 1)));
+        }
+        Ok(())
+    }
+
+    /// Returns the expressions directly inside this one.
+    pub(crate) fn children(&self) -> &[Expr] {
+        match self {
+            Expr::Operation { args: exprs, .. }
+            | Expr::Interval { interval: exprs }
+            | Expr::BBox { bbox: exprs }
+            | Expr::Array(exprs) => exprs,
+            Expr::Timestamp { timestamp: expr }
+            | Expr::Date { date: expr }
+            | Expr::Duration { duration: expr } => std::slice::from_ref(expr),
+            Expr::Property { .. }
+            | Expr::Float(_)
+            | Expr::Literal(_)
+            | Expr::Bool(_)
+            | Expr::Null
+            | Expr::Geometry(_) => &[],
+        }
+    }
+
+    /// Returns the argument of a `CASEI` call.
+    fn casei_arg(&self) -> Option<&Expr> {
+        match self {
+            Expr::Operation { op, args } if op == "casei" && args.len() == 1 => Some(&args[0]),
+            _ => None,
+        }
+    }
+
+    /// Drops this expression without recursing, for expressions that might be
+    /// too deep to drop normally.
+    pub(crate) fn drop_iteratively(self) {
+        let mut stack = vec![self];
+        while let Some(mut expr) = stack.pop() {
+            match &mut expr {
+                Expr::Operation { args: exprs, .. }
+                | Expr::Interval { interval: exprs }
+                | Expr::BBox { bbox: exprs }
+                | Expr::Array(exprs) => stack.append(exprs),
+                Expr::Timestamp { timestamp: expr }
+                | Expr::Date { date: expr }
+                | Expr::Duration { duration: expr } => {
+                    stack.push(std::mem::replace(&mut **expr, Expr::Null))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Calls `f` on this expression and every expression inside it, parents
+    /// before children.
+    fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        let mut stack = vec![self];
+        while let Some(expr) = stack.pop() {
+            f(expr);
+            stack.extend(expr.children().iter().rev());
+        }
+    }
+
+    /// Returns this expression's value as a number, if it is a constant number.
+    ///
+    /// cql2-text negative numbers are parsed as a multiplication by -1, so
+    /// those are folded as well.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Expr::Float(v) => Some(*v),
+            Expr::Operation { op, args } if op == "*" && args.len() == 2 => {
+                Some(args[0].as_f64()? * args[1].as_f64()?)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Adds a SQL parameter and returns its placeholder, or returns the value as a
+/// SQL literal if [ToSqlOptions::inline_params] is set.
+fn param(params: &mut Vec<String>, options: &ToSqlOptions, value: String, quote: bool) -> String {
+    if !options.inline_params {
+        params.push(value);
+        format!("${}", params.len())
+    } else if quote {
+        format!("'{}'", value.replace('\'', "''"))
+    } else {
+        value
+    }
+}
+
+/// Returns the number of arguments that an operator's SQL needs, if it's
+/// fixed.
+fn sql_arity(op: &str) -> Option<usize> {
+    match op {
+        "not" | "isNull" => Some(1),
+        "+" | "-" | "*" | "/" | "%" | "^" | "=" | "<=" | "<" | "<>" | ">" | ">=" | "div" => Some(2),
+        "between" => Some(3),
+        _ => None,
+    }
+}
+
+/// Returns the SQL for a property.
+///
+/// [Columns](ToSqlOptions::columns) take precedence, then queryables with an
+/// `x-column`, then [JSONB accessors](ToSqlOptions::jsonb), which are cast to
+/// the queryable type or, without one, to `hint`. Otherwise, the property is
+/// a quoted identifier.
+fn property_sql(
+    property: &str,
+    hint: Option<Type>,
+    options: &ToSqlOptions,
+) -> Result<String, Error> {
+    if let Some(column) = options.columns.get(property) {
+        return Ok(column.clone());
+    }
+    let mut property_type = hint;
+    if let Some(queryables) = &options.queryables {
+        let schema = queryables
+            .get(property)
+            .ok_or_else(|| Error::UnknownProperty(property.to_string()))?;
+        if options.jsonb.is_none() || schema.get("x-column").is_some() {
+            return Ok(queryables
+                .sql_column(property)
+                .expect("the property is a queryable"));
+        }
+        property_type = queryables.get_type(property).or(hint);
+    }
+    Ok(match &options.jsonb {
+        Some(jsonb) => jsonb.accessor(property, property_type),
+        None => quote_sql_identifier(property),
+    })
+}
+
+/// Converts bounding box coordinates to a [Rect], or to `None` if the box
+/// crosses the antimeridian.
+///
+/// [Rect::new] normalizes its corners, which would turn a box that crosses
+/// the antimeridian into its complement, so those are never swapped.
+fn bbox_to_rect(bbox: &[Expr]) -> Result<Option<Rect>, Error> {
+    let coords = bbox
+        .iter()
+        .map(|expr| {
+            expr.as_f64().ok_or_else(|| {
+                Error::InvalidBbox(format!(
+                    "{} is not a number",
+                    expr.to_text().unwrap_or_default()
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (min, max) = match coords.len() {
+        4 => (
+            coord! { x: coords[0], y: coords[1] },
+            coord! { x: coords[2], y: coords[3] },
+        ),
+        6 => (
+            coord! { x: coords[0], y: coords[1] },
+            coord! { x: coords[3], y: coords[4] },
+        ),
+        n => {
+            return Err(Error::InvalidBbox(format!(
+                "expected 4 or 6 values, got {n}"
+            )))
+        }
+    };
+    if min.x > max.x {
+        Ok(None)
+    } else {
+        Ok(Some(Rect::new(min, max)))
+    }
+}
+
+impl From<Rect> for Expr {
+    fn from(rect: Rect) -> Expr {
+        Expr::BBox {
+            bbox: vec![
+                Expr::Float(rect.min().x),
+                Expr::Float(rect.min().y),
+                Expr::Float(rect.max().x),
+                Expr::Float(rect.max().y),
+            ],
+        }
+    }
+}
+
+impl Expr {
+    /// Combines expressions with AND.
+    ///
+    /// Nested ANDs are flattened and `true` arguments are skipped. Returns
+    /// `true` if there are no other arguments, and the argument itself if
+    /// there's only one.
+    ///
+    /// [Expr] also implements [FromIterator] with the same behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let facets = [Some("foo = 1"), None, Some("bar = 2")];
+    /// let expr = Expr::and_all(
+    ///     facets
+    ///         .into_iter()
+    ///         .flatten()
+    ///         .map(|s| s.parse::<Expr>().unwrap()),
+    /// );
+    /// assert_eq!(expr.to_text().unwrap(), "((foo = 1) AND (bar = 2))");
+    ///
+    /// let expr: Expr = std::iter::empty().collect();
+    /// assert_eq!(expr, Expr::Bool(true));
+    /// ```
+    pub fn and_all(exprs: impl IntoIterator<Item = Expr>) -> Expr {
+        Expr::combine_all("and", true, exprs)
+    }
+
+    /// Combines expressions with OR.
+    ///
+    /// Nested ORs are flattened and `false` arguments are skipped. Returns
+    /// `false` if there are no other arguments, and the argument itself if
+    /// there's only one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr = Expr::or_all(["foo = 1", "bar = 2"].map(|s| s.parse().unwrap()));
+    /// assert_eq!(expr.to_text().unwrap(), "((foo = 1) OR (bar = 2))");
+    /// ```
+    pub fn or_all(exprs: impl IntoIterator<Item = Expr>) -> Expr {
+        Expr::combine_all("or", false, exprs)
+    }
+
+    fn combine_all(
+        op: &'static str,
+        identity: bool,
+        exprs: impl IntoIterator<Item = Expr>,
+    ) -> Expr {
+        exprs
+            .into_iter()
+            .filter(|expr| *expr != Expr::Bool(identity))
+            .reduce(|a, b| a.combine(op, b))
+            .unwrap_or(Expr::Bool(identity))
+    }
+
+    /// Combines two expressions with a boolean operator, flattening nested
+    /// operations with the same operator.
+    fn combine(self, op: &'static str, other: Expr) -> Expr {
+        let mut args = Vec::new();
+        for expr in [self, other] {
+            match expr {
+                Expr::Operation {
+                    op: expr_op,
+                    args: expr_args,
+                } if expr_op == op => args.extend(expr_args),
+                expr => args.push(expr),
             }
+        }
+        Expr::Operation {
+            op: op.into(),
+            args,
+        }
+    }
+}
+
+/// The binary comparison operators.
+pub(crate) const COMPARISON_OPS: [&str; 6] = ["=", "<>", "<", "<=", ">", ">="];
+
+/// The arithmetic operators.
+pub(crate) const ARITHMETIC_OPS: [&str; 7] = ["+", "-", "*", "/", "%", "^", "div"];
+
+/// Operators that are stored without allocating, see [intern_op].
+const KNOWN_OPS: [&str; 52] = [
+    "and",
+    "or",
+    "not",
+    "=",
+    "<>",
+    "<",
+    "<=",
+    ">",
+    ">=",
+    "like",
+    "in",
+    "between",
+    "isNull",
+    "casei",
+    "accenti",
+    "+",
+    "-",
+    "*",
+    "/",
+    "%",
+    "^",
+    "div",
+    "s_contains",
+    "s_crosses",
+    "s_disjoint",
+    "s_equals",
+    "s_intersects",
+    "s_overlaps",
+    "s_touches",
+    "s_within",
+    "t_after",
+    "t_before",
+    "t_contains",
+    "t_disjoint",
+    "t_during",
+    "t_equals",
+    "t_finishedby",
+    "t_finishes",
+    "t_intersects",
+    "t_meets",
+    "t_metby",
+    "t_overlappedby",
+    "t_overlaps",
+    "t_startedby",
+    "t_starts",
+    "a_containedby",
+    "a_contains",
+    "a_equals",
+    "a_overlaps",
+    "anyinteracts",
+    "bbox",
+    "now",
+];
+
+/// Double quotes a SQL identifier, doubling any double quotes inside it.
+pub(crate) fn quote_sql_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// The magnitude from which ryu writes whole numbers with an exponent.
+///
+/// Every whole `f64` below it fits in an `i64`.
+const MIN_EXPONENT_INTEGER: f64 = 1e16;
+
+/// Formats a number as its shortest round-trip representation.
+///
+/// Whole numbers below 10^16 are written without a fractional part, e.g. `2`
+/// rather than `2.0`, and larger or very small numbers use an exponent, e.g.
+/// `1e16` or `1.5e-7`.
+fn format_number(v: f64) -> String {
+    if !v.is_finite() {
+        v.to_string()
+    } else if v.fract() == 0.0 && v.abs() < MIN_EXPONENT_INTEGER {
+        // `-0.0 as i64` is `0`, which is what we want.
+        (v as i64).to_string()
+    } else {
+        ryu::Buffer::new().format_finite(v).to_string()
+    }
+}
+
+/// Serializes whole numbers as integers, so that e.g. `10` in cql2-json
+/// round-trips as `10` rather than `10.0`.
+fn serialize_number<S: serde::Serializer>(v: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if v.fract() == 0.0 && v.abs() < MIN_EXPONENT_INTEGER {
+        serializer.serialize_i64(*v as i64)
+    } else {
+        serializer.serialize_f64(*v)
+    }
+}
+
+/// Returns true if this is the (lowercase) name of a built-in operator or
+/// function.
+pub(crate) fn is_known_op(op: &str) -> bool {
+    KNOWN_OPS.contains(&op) || op == "isnull"
+}
+
+/// Returns an operator name that borrows a static string if the operator is
+/// a common one, so that parsing and cloning it doesn't allocate.
+///
+/// This is also where spellings of `isNull` like `isnull` and `is null` are
+/// normalized, so the rest of the crate only has to match `isNull`.
+pub(crate) fn intern_op(op: impl AsRef<str> + Into<String>) -> Cow<'static, str> {
+    if ["isnull", "is null"]
+        .iter()
+        .any(|name| op.as_ref().eq_ignore_ascii_case(name))
+    {
+        return Cow::Borrowed("isNull");
+    }
+    match KNOWN_OPS.iter().find(|known| **known == op.as_ref()) {
+        Some(known) => Cow::Borrowed(known),
+        None => Cow::Owned(op.into()),
+    }
+}
+
+fn deserialize_op<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Cow<'static, str>, D::Error> {
+    String::deserialize(deserializer).map(intern_op)
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f).map_err(|_| fmt::Error)
+    }
+}
+
+impl FromIterator<Expr> for Expr {
+    fn from_iter<I: IntoIterator<Item = Expr>>(iter: I) -> Expr {
+        Expr::and_all(iter)
+    }
+}
+
+impl BitAnd for Expr {
+    type Output = Expr;
+
+    fn bitand(self, other: Expr) -> Expr {
+        self.combine("and", other)
+    }
+}
+
+impl BitOr for Expr {
+    type Output = Expr;
+
+    fn bitor(self, other: Expr) -> Expr {
+        self.combine("or", other)
+    }
+}
+
+impl Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Operation {
+            op: "not".into(),
+            args: vec![self],
+        }
+    }
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (
+                Expr::Operation { op, args },
+                Expr::Operation {
+                    op: other_op,
+                    args: other_args,
+                },
+            ) => op == other_op && args == other_args,
+            (Expr::Interval { interval }, Expr::Interval { interval: other }) => interval == other,
+            (Expr::Timestamp { timestamp }, Expr::Timestamp { timestamp: other }) => {
+                timestamp == other
+            }
+            (Expr::Date { date }, Expr::Date { date: other }) => date == other,
+            (Expr::Duration { duration }, Expr::Duration { duration: other }) => duration == other,
+            (Expr::Property { property }, Expr::Property { property: other }) => property == other,
+            (Expr::BBox { bbox }, Expr::BBox { bbox: other }) => bbox == other,
+            (Expr::Float(v), Expr::Float(other)) => float_bits(*v) == float_bits(*other),
+            (Expr::Literal(v), Expr::Literal(other)) => v == other,
+            (Expr::Bool(v), Expr::Bool(other)) => v == other,
+            (Expr::Null, Expr::Null) => true,
+            (Expr::Array(v), Expr::Array(other)) => v == other,
+            (Expr::Geometry(v), Expr::Geometry(other)) => v == other,
             _ => false,
         }
     }
 }
 
+impl Eq for Expr {}
+
+impl Hash for Expr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Expr::Operation { op, args } => {
+                op.hash(state);
+                args.hash(state);
+            }
+            Expr::Interval { interval } => interval.hash(state),
+            Expr::Timestamp { timestamp } => timestamp.hash(state),
+            Expr::Date { date } => date.hash(state),
+            Expr::Duration { duration } => duration.hash(state),
+            Expr::Property { property } => property.hash(state),
+            Expr::BBox { bbox } => bbox.hash(state),
+            Expr::Float(v) => float_bits(*v).hash(state),
+            Expr::Literal(v) => v.hash(state),
+            Expr::Bool(v) => v.hash(state),
+            Expr::Null => {}
+            Expr::Array(v) => v.hash(state),
+            Expr::Geometry(v) => v.hash(state),
+        }
+    }
+}
+
+/// Returns the bit pattern of a number for equality and hashing, with `-0.0`
+/// normalized to `0.0`.
+pub(crate) fn float_bits(v: f64) -> u64 {
+    if v == 0. {
+        0
+    } else {
+        v.to_bits()
+    }
+}
+
 impl FromStr for Expr {
     type Err = Error;
 
@@ -292,6 +1276,9 @@ impl FromStr for Expr {
 #[cfg(test)]
 mod tests {
     use super::Expr;
+    use crate::{Error, GeometryFormat, ToSqlOptions};
+    use geo_types::{coord, Rect};
+    use std::{borrow::Cow, collections::HashSet};
 
     #[test]
     fn keep_z() {
@@ -319,4 +1306,377 @@ mod tests {
             point.to_text().unwrap()
         );
     }
+
+    #[test]
+    fn precision() {
+        let expr: Expr =
+            r#"{"type":"LineString","coordinates":[[0.1234567890123,-0.0000001],[1e-7,2.5]]}"#
+                .parse()
+                .unwrap();
+        assert_eq!(
+            "LINESTRING(0.123 0,0 2.5)",
+            expr.to_text_with_precision(3).unwrap()
+        );
+        let point: Expr = "POINT ZM(-105.1019 40.1672 4981.25 42)".parse().unwrap();
+        assert_eq!(
+            "POINT ZM(-105 40 4981 42)",
+            point.to_text_with_precision(0).unwrap()
+        );
+    }
+
+    #[test]
+    fn geometry_format() {
+        let expr: Expr = "s_intersects(geometry, POINT(0 0))".parse().unwrap();
+        let sql = |geometry_format| {
+            expr.to_sql_with_options(&ToSqlOptions {
+                srid: 3857,
+                geometry_format,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            sql(GeometryFormat::EpsgWkt).params,
+            ["EPSG:3857;POINT(0 0)"]
+        );
+        assert_eq!(sql(GeometryFormat::Ewkt).params, ["SRID=3857;POINT(0 0)"]);
+        assert_eq!(sql(GeometryFormat::Wkt).params, ["POINT(0 0)"]);
+        let query = sql(GeometryFormat::SetSrid);
+        assert_eq!(query.params, ["POINT(0 0)"]);
+        assert_eq!(
+            query.query,
+            "s_intersects(\"geometry\", ST_SetSRID(ST_GeomFromText($1), 3857))"
+        );
+    }
+
+    #[test]
+    fn columns() {
+        let expr: Expr = "eo:cloud_cover < 10 AND platform = 'landsat-8'"
+            .parse()
+            .unwrap();
+        let mut options = ToSqlOptions::default();
+        let _ = options.columns.insert(
+            "eo:cloud_cover".to_string(),
+            "(properties->>'eo:cloud_cover')::float".to_string(),
+        );
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            "(((properties->>'eo:cloud_cover')::float < $1) AND (\"platform\" = $2))"
+        );
+    }
+
+    #[test]
+    fn open_interval_sql() {
+        let expr: Expr = "t_intersects(datetime, INTERVAL('2020-01-01', '..'))"
+            .parse()
+            .unwrap();
+        let sql = expr.to_sql().unwrap();
+        assert_eq!(sql.query, "t_intersects(\"datetime\", TSTZRANGE($1,NULL))");
+        assert_eq!(sql.params, ["2020-01-01"]);
+    }
+
+    #[test]
+    fn inline_params() {
+        let expr: Expr =
+            "name = 'Brien' AND height > 1.5 AND t_after(datetime, DATE('2020-01-01')) AND s_intersects(geometry, POINT(0 0))"
+                .parse()
+                .unwrap();
+        let options = ToSqlOptions {
+            inline_params: true,
+            geometry_format: GeometryFormat::SetSrid,
+            ..Default::default()
+        };
+        let sql = expr.to_sql_with_options(&options).unwrap();
+        assert_eq!(
+            sql.query,
+            "((\"name\" = 'Brien') AND (\"height\" > 1.5) AND t_after(\"datetime\", '2020-01-01'::date) AND s_intersects(\"geometry\", ST_SetSRID(ST_GeomFromText('POINT(0 0)'), 4326)))"
+        );
+        assert!(sql.params.is_empty());
+        let expr: Expr = r#"{"op": "=", "args": [{"property": "name"}, "O'Brien"]}"#
+            .parse()
+            .unwrap();
+        let sql = expr.to_sql_with_options(&options).unwrap();
+        assert_eq!(sql.query, "(\"name\" = 'O''Brien')");
+    }
+
+    #[test]
+    fn now_sql() {
+        let expr: Expr = "t_after(datetime, now() - 'P7D')".parse().unwrap();
+        let sql = expr.to_sql().unwrap();
+        assert_eq!(sql.query, "t_after(\"datetime\", (now() - $1::interval))");
+        assert_eq!(sql.params, ["P7D"]);
+    }
+
+    #[test]
+    fn like_sql() {
+        let sql = |s: &str| s.parse::<Expr>().unwrap().to_sql().unwrap().query;
+        assert_eq!(sql("a LIKE 'x%'"), r#"("a" LIKE $1 ESCAPE '\')"#);
+        assert_eq!(sql("a NOT LIKE 'x%'"), r#"(NOT ("a" LIKE $1 ESCAPE '\'))"#);
+        assert_eq!(
+            sql("CASEI(a) LIKE CASEI('x%')"),
+            r#"("a" ILIKE $1 ESCAPE '\')"#
+        );
+        assert_eq!(
+            sql("ACCENTI(a) LIKE ACCENTI('x%')"),
+            r#"(accenti("a") LIKE accenti($1) ESCAPE '\')"#
+        );
+        assert_eq!(
+            sql("CASEI(a) LIKE 'x%'"),
+            r#"(casei("a") LIKE $1 ESCAPE '\')"#
+        );
+    }
+
+    #[test]
+    fn is_null() {
+        for (text, sql) in [
+            ("a IS NULL", r#"("a" IS NULL)"#),
+            ("a IS NOT NULL", r#"(NOT ("a" IS NULL))"#),
+        ] {
+            let expr: Expr = text.parse().unwrap();
+            let json = expr.to_json().unwrap();
+            assert!(json.contains(r#""op":"isNull""#), "{json}");
+            let expr: Expr = json.parse().unwrap();
+            assert_eq!(expr.to_sql().unwrap().query, sql);
+            assert_eq!(expr.to_text().unwrap().parse::<Expr>().unwrap(), expr);
+        }
+        for op in ["isnull", "ISNULL", "is null"] {
+            let expr: Expr = format!(r#"{{"op": "{op}", "args": [{{"property": "a"}}]}}"#)
+                .parse()
+                .unwrap();
+            assert_eq!(expr.to_text().unwrap(), "(a IS NULL)");
+        }
+    }
+
+    #[test]
+    fn queryables_sql() {
+        let queryables = r#"{"properties": {
+            "a": {"type": "integer", "x-column": "properties->>'a'"},
+            "b": {"type": "string"},
+            "c": {"type": "boolean", "x-column": "c"}
+        }}"#
+        .parse()
+        .unwrap();
+        let mut options = ToSqlOptions::default().with_queryables(queryables);
+        let _ = options
+            .columns
+            .insert("c".to_string(), "custom".to_string());
+        let expr: Expr = "a = 1 AND b = 'x' AND c".parse().unwrap();
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            r#"(((properties->>'a')::numeric = $1) AND ("b" = $2) AND custom)"#
+        );
+        let expr: Expr = "d = 1".parse().unwrap();
+        assert!(matches!(
+            expr.to_sql_with_options(&options),
+            Err(Error::UnknownProperty(property)) if property == "d"
+        ));
+    }
+
+    #[test]
+    fn numbers() {
+        for (text, expected) in [
+            ("2.0", "2"),
+            ("0.1", "0.1"),
+            ("1e300", "1e300"),
+            ("1.5e-7", "1.5e-7"),
+            ("9007199254740992", "9007199254740992"),
+            ("9999999999999998", "9999999999999998"),
+            ("1e16", "1e16"),
+            ("12345678901234567890", "1.2345678901234567e19"),
+        ] {
+            let expr: Expr = format!("a = {text}").parse().unwrap();
+            assert_eq!(expr.to_text().unwrap(), format!("(a = {expected})"));
+            assert_eq!(
+                expr.to_json().unwrap(),
+                format!(r#"{{"op":"=","args":[{{"property":"a"}},{expected}]}}"#)
+            );
+            assert_eq!(expr.to_sql().unwrap().params, [expected]);
+            assert_eq!(expr.to_text().unwrap().parse::<Expr>().unwrap(), expr);
+        }
+    }
+
+    #[test]
+    fn div() {
+        let expr: Expr = "a div 2 = 1".parse().unwrap();
+        assert_eq!(expr.to_text().unwrap(), "(a div 2 = 1)");
+        assert_eq!(expr.to_text().unwrap().parse::<Expr>().unwrap(), expr);
+        assert_eq!(expr.to_sql().unwrap().query, "(floor(\"a\" / $1) = $2)");
+    }
+
+    #[test]
+    fn sql_wrong_number_of_arguments() {
+        for json in [
+            r#"{"op": "=", "args": [{"property": "a"}]}"#,
+            r#"{"op": "between", "args": [{"property": "a"}, 1]}"#,
+            r#"{"op": "not", "args": []}"#,
+            r#"{"op": "t_after", "args": [{"property": "a"}, {"interval": ["2020-01-01"]}]}"#,
+        ] {
+            let expr: Expr = json.parse().unwrap();
+            assert!(matches!(
+                expr.to_sql(),
+                Err(Error::InvalidNumberOfArguments { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut expr = Expr::Bool(true);
+        for _ in 0..300 {
+            expr = Expr::Operation {
+                op: "not".into(),
+                args: vec![expr],
+            };
+        }
+        for result in [expr.to_text(), expr.to_text_pretty()] {
+            assert!(matches!(
+                result.unwrap_err(),
+                Error::MaxDepthExceeded(crate::DEFAULT_MAX_DEPTH)
+            ));
+        }
+        assert!(matches!(
+            expr.to_sql().unwrap_err(),
+            Error::MaxDepthExceeded(crate::DEFAULT_MAX_DEPTH)
+        ));
+    }
+
+    #[test]
+    fn bbox_roundtrip() {
+        let rect = Rect::new(coord! { x: -105., y: 40. }, coord! { x: -104., y: 41. });
+        let expr = Expr::from(rect);
+        assert_eq!(expr.to_text().unwrap(), "BBOX(-105, 40, -104, 41)");
+        assert_eq!(expr.bounding_box().unwrap().unwrap(), rect);
+    }
+
+    #[test]
+    fn bbox_3d() {
+        let expr: Expr = "BBOX(-105, 40, 0, -104, 41, 100)".parse().unwrap();
+        assert_eq!(
+            expr.bounding_box().unwrap().unwrap(),
+            Rect::new(coord! { x: -105., y: 40. }, coord! { x: -104., y: 41. })
+        );
+    }
+
+    #[test]
+    fn bbox_antimeridian() {
+        let expr: Expr = "BBOX(170, -10, -170, 10)".parse().unwrap();
+        assert!(expr.bounding_box().unwrap().is_none());
+        let expr: Expr = "BBOX(170, -10, 0, -170, 10, 100)".parse().unwrap();
+        assert!(expr.bounding_box().unwrap().is_none());
+    }
+
+    #[test]
+    fn bbox_invalid() {
+        let expr: Expr = "BBOX(-105, 40, -104)".parse().unwrap();
+        let _ = expr.bounding_box().unwrap_err();
+        let expr: Expr = "BBOX(-105, 40, -104, foo)".parse().unwrap();
+        let _ = expr.bounding_box().unwrap_err();
+    }
+
+    #[test]
+    fn null() {
+        let expr: Expr = "foo = null".parse().unwrap();
+        assert_eq!(expr.to_text().unwrap(), "(foo = NULL)");
+        assert_eq!(
+            expr.to_json().unwrap(),
+            r#"{"op":"=","args":[{"property":"foo"},null]}"#
+        );
+        assert_eq!(expr.to_sql().unwrap().query, "(\"foo\" = NULL)");
+        let expr: Expr = expr.to_json().unwrap().parse().unwrap();
+        assert_eq!(expr.to_text().unwrap(), "(foo = NULL)");
+    }
+
+    #[test]
+    fn eq_and_hash() {
+        let a: Expr = "foo = 42 AND s_intersects(geometry, POINT(0 0))"
+            .parse()
+            .unwrap();
+        let b: Expr = "foo = 42 AND s_intersects(geometry, POINT(0 0))"
+            .parse()
+            .unwrap();
+        let c: Expr = "foo = 43 AND s_intersects(geometry, POINT(0 0))"
+            .parse()
+            .unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        let set: HashSet<Expr> = [a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn eq_float_bits() {
+        assert_eq!(Expr::Float(f64::NAN), Expr::Float(f64::NAN));
+        assert_eq!(Expr::Float(0.0), Expr::Float(-0.0));
+        assert_ne!(Expr::Float(0.0), Expr::Float(f64::MIN_POSITIVE));
+        let expr = Expr::Float(-0.0);
+        let set: HashSet<Expr> = [expr.clone(), expr.to_text().unwrap().parse().unwrap()]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn boolean_operators_flatten() {
+        let a: Expr = "a = 1".parse().unwrap();
+        let b: Expr = "b = 2".parse().unwrap();
+        let c: Expr = "c = 3".parse().unwrap();
+        let expr = a.clone() & b.clone() & c.clone();
+        assert_eq!(expr, "a = 1 AND b = 2 AND c = 3".parse().unwrap());
+        let expr = a | (b | c);
+        assert_eq!(expr.to_text().unwrap(), "((a = 1) OR (b = 2) OR (c = 3))");
+    }
+
+    #[test]
+    fn and_all() {
+        let exprs: Vec<Expr> = vec![
+            "a = 1".parse().unwrap(),
+            Expr::Bool(true),
+            "b = 2 AND c = 3".parse().unwrap(),
+        ];
+        let expr: Expr = exprs.into_iter().collect();
+        assert_eq!(expr, "a = 1 AND b = 2 AND c = 3".parse().unwrap());
+        let expr = Expr::and_all([Expr::Bool(true), "a = 1".parse().unwrap()]);
+        assert_eq!(expr, "a = 1".parse().unwrap());
+    }
+
+    #[test]
+    fn or_all() {
+        assert_eq!(Expr::or_all([]), Expr::Bool(false));
+        let expr = Expr::or_all([Expr::Bool(false), Expr::Bool(false)]);
+        assert_eq!(expr, Expr::Bool(false));
+        let expr = Expr::or_all(["a = 1".parse().unwrap(), Expr::Bool(true)]);
+        assert_eq!(expr.to_text().unwrap(), "((a = 1) OR true)");
+    }
+
+    #[test]
+    fn known_operators_are_not_allocated() {
+        for expr in [
+            "a = 1 AND s_intersects(geometry, POINT(0 0))"
+                .parse::<Expr>()
+                .unwrap(),
+            r#"{"op": "and", "args": [{"op": "=", "args": [{"property": "a"}, 1]}, true]}"#
+                .parse()
+                .unwrap(),
+        ] {
+            let Expr::Operation { op, args } = expr else {
+                panic!("expected an operation");
+            };
+            assert!(matches!(op, Cow::Borrowed("and")));
+            assert!(matches!(
+                &args[0],
+                Expr::Operation {
+                    op: Cow::Borrowed(_),
+                    ..
+                }
+            ));
+        }
+        let expr: Expr = "my_function(a)".parse().unwrap();
+        assert!(matches!(
+            expr,
+            Expr::Operation {
+                op: Cow::Owned(_),
+                ..
+            }
+        ));
+    }
 }
@@ -0,0 +1,421 @@
+use crate::{DateRange, Error, Expr};
+use geo_types::{coord, Rect};
+use jiff::Timestamp;
+
+/// The STAC property that holds an item's geometry.
+const GEOMETRY_PROPERTY: &str = "geometry";
+
+/// The STAC properties that hold an item's temporal extent.
+pub(crate) const DATETIME_PROPERTIES: [&str; 3] = ["datetime", "start_datetime", "end_datetime"];
+
+/// Spatial operators that can only match if the item's geometry intersects the
+/// other operand.
+const INTERSECTING_SPATIAL_OPS: [&str; 7] = [
+    "s_intersects",
+    "s_equals",
+    "s_within",
+    "s_contains",
+    "s_overlaps",
+    "s_touches",
+    "s_crosses",
+];
+
+/// Temporal operators that can only match if the item's temporal extent
+/// intersects the other operand.
+const INTERSECTING_TEMPORAL_OPS: [&str; 13] = [
+    "t_intersects",
+    "t_equals",
+    "t_during",
+    "t_starts",
+    "t_startedby",
+    "t_finishes",
+    "t_finishedby",
+    "t_contains",
+    "t_overlaps",
+    "t_overlappedby",
+    "t_meets",
+    "t_metby",
+    "anyinteracts",
+];
+
+/// The extent of the items that could match an expression, as returned by
+/// [Expr::spatial_extent] and [Expr::temporal_extent].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extent<T> {
+    /// The expression doesn't constrain this dimension, so any item could
+    /// match.
+    Unbounded,
+
+    /// No item can match, e.g. because the expression requires disjoint
+    /// extents.
+    Empty,
+
+    /// Every item that could match is within this extent.
+    Bounded(T),
+}
+
+impl<T> Extent<T> {
+    /// Returns the bounded extent, or `None` if the extent is unbounded or
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Extent;
+    ///
+    /// assert_eq!(Extent::Bounded(42).bounded(), Some(42));
+    /// assert_eq!(Extent::<i32>::Empty.bounded(), None);
+    /// ```
+    pub fn bounded(self) -> Option<T> {
+        match self {
+            Extent::Bounded(extent) => Some(extent),
+            Extent::Unbounded | Extent::Empty => None,
+        }
+    }
+
+    /// Intersects the extents of the arguments of an AND.
+    fn and(
+        extents: impl IntoIterator<Item = Result<Extent<T>, Error>>,
+        intersection: impl Fn(&T, &T) -> Option<T>,
+    ) -> Result<Extent<T>, Error> {
+        let mut result = Extent::Unbounded;
+        for extent in extents {
+            result = match (result, extent?) {
+                (Extent::Empty, _) | (_, Extent::Empty) => Extent::Empty,
+                (Extent::Unbounded, extent) | (extent, Extent::Unbounded) => extent,
+                (Extent::Bounded(a), Extent::Bounded(b)) => match intersection(&a, &b) {
+                    Some(extent) => Extent::Bounded(extent),
+                    None => Extent::Empty,
+                },
+            };
+        }
+        Ok(result)
+    }
+
+    /// Unions the extents of the arguments of an OR.
+    fn or(
+        extents: impl IntoIterator<Item = Result<Extent<T>, Error>>,
+        union: impl Fn(&T, &T) -> T,
+    ) -> Result<Extent<T>, Error> {
+        let mut result = Extent::Empty;
+        for extent in extents {
+            result = match (result, extent?) {
+                (Extent::Unbounded, _) | (_, Extent::Unbounded) => return Ok(Extent::Unbounded),
+                (Extent::Empty, extent) | (extent, Extent::Empty) => extent,
+                (Extent::Bounded(a), Extent::Bounded(b)) => Extent::Bounded(union(&a, &b)),
+            };
+        }
+        Ok(result)
+    }
+}
+
+impl<T> From<Option<T>> for Extent<T> {
+    fn from(extent: Option<T>) -> Extent<T> {
+        match extent {
+            Some(extent) => Extent::Bounded(extent),
+            None => Extent::Unbounded,
+        }
+    }
+}
+
+impl Expr {
+    /// Returns a bounding box that contains the geometry of every item that
+    /// could match this expression.
+    ///
+    /// The extent is derived conservatively from spatial operations on the
+    /// STAC `geometry` property, and can be used to pre-filter items (e.g.
+    /// with a spatial index) before evaluating the full expression. Returns
+    /// [Extent::Unbounded] if the expression doesn't constrain the geometry,
+    /// including for bounding boxes that cross the antimeridian, and
+    /// [Extent::Empty] if no item can match, e.g. `false` or an AND of
+    /// disjoint bounding boxes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Extent};
+    ///
+    /// let expr: Expr = "s_intersects(geometry, BBOX(-105, 40, -104, 41)) AND foo = 42"
+    ///     .parse()
+    ///     .unwrap();
+    /// let rect = expr.spatial_extent().unwrap().bounded().unwrap();
+    /// assert_eq!(rect.min().x, -105.);
+    ///
+    /// let expr: Expr = "s_disjoint(geometry, POINT(-105 40))".parse().unwrap();
+    /// assert_eq!(expr.spatial_extent().unwrap(), Extent::Unbounded);
+    ///
+    /// let expr: Expr = "s_intersects(geometry, BBOX(0, 0, 1, 1)) \
+    ///     AND s_intersects(geometry, BBOX(2, 2, 3, 3))"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(expr.spatial_extent().unwrap(), Extent::Empty);
+    /// ```
+    pub fn spatial_extent(&self) -> Result<Extent<Rect>, Error> {
+        let Expr::Operation { op, args } = self else {
+            return Ok(self.constant_extent());
+        };
+        match op.as_ref() {
+            "and" => Extent::and(args.iter().map(Expr::spatial_extent), intersect_rects),
+            "or" => Extent::or(args.iter().map(Expr::spatial_extent), union_rects),
+            op if INTERSECTING_SPATIAL_OPS.contains(&op) && args.len() == 2 => {
+                match (&args[0], &args[1]) {
+                    (Expr::Property { property }, other) | (other, Expr::Property { property })
+                        if property == GEOMETRY_PROPERTY =>
+                    {
+                        other.bounding_box().map(Extent::from)
+                    }
+                    _ => Ok(Extent::Unbounded),
+                }
+            }
+            _ => Ok(Extent::Unbounded),
+        }
+    }
+
+    /// Returns a date range that intersects the temporal extent of every item
+    /// that could match this expression.
+    ///
+    /// The extent is derived conservatively from comparison and temporal
+    /// operations on the STAC `datetime`, `start_datetime`, and `end_datetime`
+    /// properties. Returns [Extent::Unbounded] if the expression doesn't
+    /// constrain time, and [Extent::Empty] if no item can match, e.g. `false`
+    /// or an AND of disjoint ranges.
+    ///
+    /// Half-bounded ranges (e.g. from `datetime > TIMESTAMP(...)`) use
+    /// [Timestamp::MIN] or [Timestamp::MAX] for their open end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "t_intersects(datetime, INTERVAL('2020-01-01', '2020-12-31')) AND foo = 42"
+    ///     .parse()
+    ///     .unwrap();
+    /// let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+    /// assert_eq!(date_range.start.to_string(), "2020-01-01T00:00:00Z");
+    /// ```
+    pub fn temporal_extent(&self) -> Result<Extent<DateRange>, Error> {
+        let Expr::Operation { op, args } = self else {
+            return Ok(self.constant_extent());
+        };
+        match op.as_ref() {
+            "and" => Extent::and(
+                args.iter().map(Expr::temporal_extent),
+                DateRange::intersection,
+            ),
+            "or" => Extent::or(args.iter().map(Expr::temporal_extent), DateRange::union),
+            "between" if args.len() == 3 && args[0].is_datetime_property() => {
+                let low = args[1].date_range()?;
+                let high = args[2].date_range()?;
+                Ok(low
+                    .zip(high)
+                    .map(|(low, high)| DateRange {
+                        start: low.start,
+                        end: high.end,
+                    })
+                    .into())
+            }
+            op if args.len() == 2 => {
+                let (op, other) = if args[0].is_datetime_property() {
+                    (op.to_string(), &args[1])
+                } else if args[1].is_datetime_property() {
+                    (mirror(op), &args[0])
+                } else {
+                    return Ok(Extent::Unbounded);
+                };
+                let Some(date_range) = other.date_range()? else {
+                    return Ok(Extent::Unbounded);
+                };
+                let extent = match op.as_str() {
+                    "=" => Some(date_range),
+                    "<" | "<=" | "t_before" => Some(DateRange {
+                        start: Timestamp::MIN,
+                        end: date_range.end,
+                    }),
+                    ">" | ">=" | "t_after" => Some(DateRange {
+                        start: date_range.start,
+                        end: Timestamp::MAX,
+                    }),
+                    op if INTERSECTING_TEMPORAL_OPS.contains(&op) => Some(date_range),
+                    _ => None,
+                };
+                Ok(extent.into())
+            }
+            _ => Ok(Extent::Unbounded),
+        }
+    }
+
+    /// Returns [Extent::Empty] for `false`, which matches nothing.
+    fn constant_extent<T>(&self) -> Extent<T> {
+        if *self == Expr::Bool(false) {
+            Extent::Empty
+        } else {
+            Extent::Unbounded
+        }
+    }
+
+    fn is_datetime_property(&self) -> bool {
+        match self {
+            Expr::Property { property } => DATETIME_PROPERTIES.contains(&property.as_str()),
+            Expr::Interval { interval } => interval.iter().all(|arg| arg.is_datetime_property()),
+            _ => false,
+        }
+    }
+}
+
+/// Returns the operator with its operands swapped, e.g. `a < b` is `b > a`.
+fn mirror(op: &str) -> String {
+    match op {
+        "<" => ">",
+        "<=" => ">=",
+        ">" => "<",
+        ">=" => "<=",
+        "t_before" => "t_after",
+        "t_after" => "t_before",
+        op => op,
+    }
+    .to_string()
+}
+
+fn intersect_rects(a: &Rect, b: &Rect) -> Option<Rect> {
+    let min = coord! {
+        x: a.min().x.max(b.min().x),
+        y: a.min().y.max(b.min().y),
+    };
+    let max = coord! {
+        x: a.max().x.min(b.max().x),
+        y: a.max().y.min(b.max().y),
+    };
+    if min.x <= max.x && min.y <= max.y {
+        Some(Rect::new(min, max))
+    } else {
+        None
+    }
+}
+
+fn union_rects(a: &Rect, b: &Rect) -> Rect {
+    Rect::new(
+        coord! {
+            x: a.min().x.min(b.min().x),
+            y: a.min().y.min(b.min().y),
+        },
+        coord! {
+            x: a.max().x.max(b.max().x),
+            y: a.max().y.max(b.max().y),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extent;
+    use crate::Expr;
+
+    #[test]
+    fn spatial_and() {
+        let expr: Expr = "s_intersects(geometry, BBOX(0, 0, 10, 10)) AND s_intersects(geometry, BBOX(5, 5, 15, 15))"
+            .parse()
+            .unwrap();
+        let rect = expr.spatial_extent().unwrap().bounded().unwrap();
+        assert_eq!(rect.min().x, 5.);
+        assert_eq!(rect.max().x, 10.);
+    }
+
+    #[test]
+    fn spatial_or() {
+        let expr: Expr = "s_intersects(geometry, BBOX(0, 0, 10, 10)) OR s_intersects(geometry, BBOX(5, 5, 15, 15))"
+            .parse()
+            .unwrap();
+        let rect = expr.spatial_extent().unwrap().bounded().unwrap();
+        assert_eq!(rect.min().x, 0.);
+        assert_eq!(rect.max().x, 15.);
+
+        let expr: Expr = "s_intersects(geometry, BBOX(0, 0, 10, 10)) OR foo = 42"
+            .parse()
+            .unwrap();
+        assert_eq!(expr.spatial_extent().unwrap(), Extent::Unbounded);
+    }
+
+    #[test]
+    fn spatial_not() {
+        let expr: Expr = "NOT s_intersects(geometry, BBOX(0, 0, 10, 10))"
+            .parse()
+            .unwrap();
+        assert_eq!(expr.spatial_extent().unwrap(), Extent::Unbounded);
+    }
+
+    #[test]
+    fn temporal_comparisons() {
+        let expr: Expr = "datetime >= TIMESTAMP('2020-01-01T00:00:00Z') AND TIMESTAMP('2021-01-01T00:00:00Z') > datetime"
+            .parse()
+            .unwrap();
+        let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+        assert_eq!(date_range.start.to_string(), "2020-01-01T00:00:00Z");
+        assert_eq!(date_range.end.to_string(), "2021-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn temporal_stac_interval() {
+        let expr: Expr =
+            "t_during(INTERVAL(start_datetime, end_datetime), INTERVAL('2020-01-01', '2020-01-31'))"
+                .parse()
+                .unwrap();
+        let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+        assert_eq!(date_range.start.to_string(), "2020-01-01T00:00:00Z");
+        assert_eq!(date_range.end.to_string(), "2020-01-31T23:59:59.999999999Z");
+    }
+
+    #[test]
+    fn temporal_open_interval() {
+        let expr: Expr = "t_intersects(datetime, INTERVAL('..', '2020-01-01'))"
+            .parse()
+            .unwrap();
+        let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+        assert_eq!(date_range.start, jiff::Timestamp::MIN);
+        assert_eq!(date_range.end.to_string(), "2020-01-01T23:59:59.999999999Z");
+    }
+
+    #[test]
+    fn temporal_other_property() {
+        let expr: Expr = "t_after(updated, TIMESTAMP('2020-01-01T00:00:00Z'))"
+            .parse()
+            .unwrap();
+        assert_eq!(expr.temporal_extent().unwrap(), Extent::Unbounded);
+    }
+
+    #[test]
+    fn spatial_antimeridian() {
+        let expr: Expr = "s_intersects(geometry, BBOX(170, -10, -170, 10))"
+            .parse()
+            .unwrap();
+        assert_eq!(expr.spatial_extent().unwrap(), Extent::Unbounded);
+
+        let expr: Expr = "s_intersects(geometry, BBOX(170, -10, -170, 10)) \
+            AND s_intersects(geometry, BBOX(175, 0, 179, 5))"
+            .parse()
+            .unwrap();
+        let rect = expr.spatial_extent().unwrap().bounded().unwrap();
+        assert_eq!(rect.min().x, 175.);
+    }
+
+    #[test]
+    fn empty() {
+        let expr: Expr =
+            "s_intersects(geometry, BBOX(0, 0, 1, 1)) AND s_intersects(geometry, BBOX(2, 2, 3, 3))"
+                .parse()
+                .unwrap();
+        assert_eq!(expr.spatial_extent().unwrap(), Extent::Empty);
+
+        let expr: Expr = "datetime < TIMESTAMP('2020-01-01T00:00:00Z') AND datetime > TIMESTAMP('2021-01-01T00:00:00Z')"
+            .parse()
+            .unwrap();
+        assert_eq!(expr.temporal_extent().unwrap(), Extent::Empty);
+
+        let expr: Expr = "(datetime < TIMESTAMP('2020-01-01T00:00:00Z') AND false) \
+            OR datetime > TIMESTAMP('2021-01-01T00:00:00Z')"
+            .parse()
+            .unwrap();
+        let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+        assert_eq!(date_range.start.to_string(), "2021-01-01T00:00:00Z");
+    }
+}
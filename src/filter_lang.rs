@@ -0,0 +1,252 @@
+use crate::{Error, Expr, Validator};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// The only `filter-crs` that CQL2 geometries can use without the filter-crs
+/// extension.
+pub const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+
+/// An encoding of CQL2, as named by the STAC API `filter-lang` parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FilterLang {
+    /// `cql2-text`
+    #[serde(rename = "cql2-text")]
+    Cql2Text,
+
+    /// `cql2-json`
+    #[serde(rename = "cql2-json")]
+    Cql2Json,
+}
+
+/// The `filter`, `filter-lang`, and `filter-crs` parameters of a STAC API
+/// request.
+///
+/// This deserializes from GET query parameters, where the filter is a
+/// string, and from POST bodies, where it's usually a JSON object.
+///
+/// # Examples
+///
+/// ```
+/// use cql2::FilterParams;
+/// use serde_json::json;
+///
+/// let params: FilterParams = serde_json::from_value(json!({
+///     "filter": {"op": "=", "args": [{"property": "id"}, "LC08"]},
+///     "filter-lang": "cql2-json",
+/// }))
+/// .unwrap();
+/// let expr = params.to_expr().unwrap().unwrap();
+/// assert_eq!(expr.to_text().unwrap(), "(id = 'LC08')");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterParams {
+    /// The filter, as cql2-text in a string or cql2-json in a string or
+    /// object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+
+    /// The filter's encoding.
+    ///
+    /// If `None`, strings are cql2-text and anything else is cql2-json, which
+    /// matches the STAC API defaults for GET and POST requests.
+    #[serde(rename = "filter-lang", skip_serializing_if = "Option::is_none")]
+    pub filter_lang: Option<FilterLang>,
+
+    /// The coordinate reference system of the filter's geometries.
+    ///
+    /// Only [CRS84] is supported.
+    #[serde(rename = "filter-crs", skip_serializing_if = "Option::is_none")]
+    pub filter_crs: Option<String>,
+}
+
+impl FilterLang {
+    /// Returns the name of this encoding, e.g. `cql2-text`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::FilterLang;
+    ///
+    /// assert_eq!(FilterLang::Cql2Json.as_str(), "cql2-json");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterLang::Cql2Text => "cql2-text",
+            FilterLang::Cql2Json => "cql2-json",
+        }
+    }
+
+    /// Returns the conformance class of this encoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::FilterLang;
+    ///
+    /// assert_eq!(
+    ///     FilterLang::Cql2Text.conformance_class(),
+    ///     "http://www.opengis.net/spec/cql2/1.0/conf/cql2-text"
+    /// );
+    /// ```
+    pub fn conformance_class(&self) -> &'static str {
+        match self {
+            FilterLang::Cql2Text => "http://www.opengis.net/spec/cql2/1.0/conf/cql2-text",
+            FilterLang::Cql2Json => "http://www.opengis.net/spec/cql2/1.0/conf/cql2-json",
+        }
+    }
+
+    /// Picks the encoding to send to a server, given the conformance classes
+    /// from its landing page.
+    ///
+    /// cql2-json is preferred, because it can be sent in a POST body. Any
+    /// version of the conformance classes is accepted. Returns `None` if the
+    /// server doesn't support either encoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::FilterLang;
+    ///
+    /// let conforms_to = [
+    ///     "https://api.stacspec.org/v1.0.0/core",
+    ///     "http://www.opengis.net/spec/cql2/1.0/conf/cql2-text",
+    /// ];
+    /// assert_eq!(FilterLang::negotiate(conforms_to), Some(FilterLang::Cql2Text));
+    /// assert_eq!(FilterLang::negotiate(["https://api.stacspec.org/v1.0.0/core"]), None);
+    /// ```
+    pub fn negotiate(conforms_to: impl IntoIterator<Item = impl AsRef<str>>) -> Option<FilterLang> {
+        let mut supported = None;
+        for uri in conforms_to {
+            let uri = uri.as_ref();
+            if uri.ends_with("/conf/cql2-json") {
+                return Some(FilterLang::Cql2Json);
+            } else if uri.ends_with("/conf/cql2-text") {
+                supported = Some(FilterLang::Cql2Text);
+            }
+        }
+        supported
+    }
+}
+
+impl FilterParams {
+    /// Parses and validates the filter.
+    ///
+    /// Returns `None` if there's no filter. Returns an error if the filter
+    /// doesn't match its `filter-lang`, if the `filter-crs` isn't [CRS84], or
+    /// if the expression is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{FilterLang, FilterParams};
+    ///
+    /// let params = FilterParams {
+    ///     filter: Some("eo:cloud_cover < 10".into()),
+    ///     filter_lang: Some(FilterLang::Cql2Text),
+    ///     filter_crs: None,
+    /// };
+    /// let expr = params.to_expr().unwrap().unwrap();
+    /// assert_eq!(expr.to_text().unwrap(), "(\"eo:cloud_cover\" < 10)");
+    /// ```
+    pub fn to_expr(&self) -> Result<Option<Expr>, Error> {
+        let Some(filter) = &self.filter else {
+            return Ok(None);
+        };
+        if let Some(crs) = self.filter_crs.as_deref().filter(|crs| *crs != CRS84) {
+            return Err(Error::UnsupportedFilterCrs(crs.to_string()));
+        }
+        let expr = match (filter, self.filter_lang) {
+            (Value::String(s), None | Some(FilterLang::Cql2Text)) => crate::parse_text(s)?,
+            (Value::String(s), Some(FilterLang::Cql2Json)) => crate::parse_json(s)?,
+            (_, Some(FilterLang::Cql2Text)) => {
+                return Err(Error::InvalidCql2Text(filter.to_string()));
+            }
+            (value, None | Some(FilterLang::Cql2Json)) => serde_json::from_value(value.clone())?,
+        };
+        let report = Validator::default_ref().validate_expr(&expr);
+        if report.is_valid() {
+            Ok(Some(expr))
+        } else {
+            Err(Error::InvalidFilter(report))
+        }
+    }
+}
+
+impl Display for FilterLang {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FilterLang {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<FilterLang, Error> {
+        match s {
+            "cql2-text" => Ok(FilterLang::Cql2Text),
+            "cql2-json" => Ok(FilterLang::Cql2Json),
+            _ => Err(Error::UnknownFilterLang(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterLang, FilterParams, CRS84};
+    use crate::Error;
+    use serde_json::json;
+
+    fn params(value: serde_json::Value) -> FilterParams {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn filter_lang() {
+        for lang in [FilterLang::Cql2Text, FilterLang::Cql2Json] {
+            assert_eq!(lang.to_string().parse::<FilterLang>().unwrap(), lang);
+        }
+        assert!(matches!(
+            "cql-text".parse::<FilterLang>(),
+            Err(Error::UnknownFilterLang(_))
+        ));
+    }
+
+    #[test]
+    fn to_expr() {
+        assert_eq!(FilterParams::default().to_expr().unwrap(), None);
+        let expected = "(id = 'a')";
+        for params in [
+            params(json!({"filter": "id = 'a'"})),
+            params(
+                json!({"filter": r#"{"op": "=", "args": [{"property": "id"}, "a"]}"#, "filter-lang": "cql2-json"}),
+            ),
+            params(
+                json!({"filter": {"op": "=", "args": [{"property": "id"}, "a"]}, "filter-crs": CRS84}),
+            ),
+        ] {
+            let expr = params.to_expr().unwrap().unwrap();
+            assert_eq!(expr.to_text().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            params(json!({"filter": {"op": "=", "args": []}, "filter-lang": "cql2-text"}))
+                .to_expr(),
+            Err(Error::InvalidCql2Text(_))
+        ));
+        assert!(matches!(
+            params(json!({"filter": "id = 'a'", "filter-crs": "EPSG:3857"})).to_expr(),
+            Err(Error::UnsupportedFilterCrs(crs)) if crs == "EPSG:3857"
+        ));
+        assert!(matches!(
+            params(json!({"filter": "t_before(datetime, DATE('2020-13-01'))"})).to_expr(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+}
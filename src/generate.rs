@@ -0,0 +1,270 @@
+use crate::{expr::COMPARISON_OPS, Expr, Geometry};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// The deepest nesting of `AND`, `OR`, and `NOT` in generated expressions.
+const MAX_DEPTH: usize = 3;
+
+const NUMBER_PROPERTIES: [&str; 3] = ["eo:cloud_cover", "gsd", "view:off_nadir"];
+const STRING_PROPERTIES: [&str; 3] = ["id", "platform", "instruments"];
+const SPATIAL_OPS: [&str; 8] = [
+    "s_intersects",
+    "s_equals",
+    "s_disjoint",
+    "s_touches",
+    "s_within",
+    "s_overlaps",
+    "s_crosses",
+    "s_contains",
+];
+const TEMPORAL_OPS: [&str; 14] = [
+    "t_after",
+    "t_before",
+    "t_contains",
+    "t_disjoint",
+    "t_during",
+    "t_equals",
+    "t_finishedby",
+    "t_finishes",
+    "t_intersects",
+    "t_meets",
+    "t_metby",
+    "t_overlappedby",
+    "t_overlaps",
+    "t_startedby",
+];
+const ARRAY_OPS: [&str; 4] = ["a_equals", "a_contains", "a_containedby", "a_overlaps"];
+const WORDS: [&str; 6] = ["landsat-8", "sentinel-2a", "oli", "tirs", "msi", "LC08"];
+
+/// A kind of predicate that [Expr::generate] can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExprClass {
+    /// Numeric comparisons, `BETWEEN`, `IN`, and `IS NULL`.
+    Comparison,
+
+    /// String comparisons, `LIKE`, and `CASEI`.
+    Text,
+
+    /// Spatial operators on points, lines, polygons, and bounding boxes.
+    Spatial,
+
+    /// Temporal operators on dates, timestamps, and intervals.
+    Temporal,
+
+    /// Array operators.
+    Array,
+}
+
+impl ExprClass {
+    /// All of the classes.
+    pub const ALL: [ExprClass; 5] = [
+        ExprClass::Comparison,
+        ExprClass::Text,
+        ExprClass::Spatial,
+        ExprClass::Temporal,
+        ExprClass::Array,
+    ];
+}
+
+impl Expr {
+    /// Generates a random, valid expression from unstructured data.
+    ///
+    /// The expression combines predicates from the given classes with `AND`,
+    /// `OR`, and `NOT`. Properties are drawn from a small set of STAC item
+    /// properties. Requires the `arbitrary` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arbitrary::Unstructured;
+    /// use cql2::{Expr, ExprClass};
+    ///
+    /// let bytes: Vec<u8> = (0..1024).map(|i| (i * 37 % 251) as u8).collect();
+    /// let mut u = Unstructured::new(&bytes);
+    /// let expr = Expr::generate(&mut u, &[ExprClass::Spatial]).unwrap();
+    /// assert!(expr.is_valid());
+    /// ```
+    pub fn generate(u: &mut Unstructured<'_>, classes: &[ExprClass]) -> Result<Expr> {
+        let classes = if classes.is_empty() {
+            &ExprClass::ALL[..]
+        } else {
+            classes
+        };
+        generate(u, classes, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Expr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Expr> {
+        Expr::generate(u, &ExprClass::ALL)
+    }
+}
+
+fn generate(u: &mut Unstructured<'_>, classes: &[ExprClass], depth: usize) -> Result<Expr> {
+    if depth < MAX_DEPTH && u.ratio(1, 3)? {
+        return Ok(match u.int_in_range(0..=2)? {
+            0 => operation("not", vec![generate(u, classes, depth + 1)?]),
+            n => {
+                let len = u.int_in_range(2..=3)?;
+                let args = (0..len)
+                    .map(|_| generate(u, classes, depth + 1))
+                    .collect::<Result<_>>()?;
+                operation(if n == 1 { "and" } else { "or" }, args)
+            }
+        });
+    }
+    match u.choose(classes)? {
+        ExprClass::Comparison => comparison(u),
+        ExprClass::Text => text(u),
+        ExprClass::Spatial => Ok(operation(
+            u.choose(&SPATIAL_OPS)?,
+            vec![property("geometry"), geometry(u)?],
+        )),
+        ExprClass::Temporal => Ok(operation(
+            u.choose(&TEMPORAL_OPS)?,
+            vec![property("datetime"), temporal(u)?],
+        )),
+        ExprClass::Array => {
+            // cql2-text reads `('a')` as a string, not an array.
+            let len = u.int_in_range(2..=3)?;
+            let array = (0..len).map(|_| word(u)).collect::<Result<_>>()?;
+            Ok(operation(
+                u.choose(&ARRAY_OPS)?,
+                vec![property("keywords"), Expr::Array(array)],
+            ))
+        }
+    }
+}
+
+fn comparison(u: &mut Unstructured<'_>) -> Result<Expr> {
+    let p = property(u.choose(&NUMBER_PROPERTIES)?);
+    Ok(match u.int_in_range(0..=3)? {
+        0 => {
+            let (a, b) = ordered(u.int_in_range(-100..=100)?, u.int_in_range(-100..=100)?);
+            operation("between", vec![p, number(a), number(b)])
+        }
+        1 => {
+            let len = u.int_in_range(2..=4)?;
+            let array = (0..len)
+                .map(|_| Ok(number(u.int_in_range(0..=100)?)))
+                .collect::<Result<_>>()?;
+            operation("in", vec![p, Expr::Array(array)])
+        }
+        2 => operation("isNull", vec![p]),
+        _ => operation(
+            u.choose(&COMPARISON_OPS)?,
+            vec![p, number(u.int_in_range(-100..=100)?)],
+        ),
+    })
+}
+
+fn text(u: &mut Unstructured<'_>) -> Result<Expr> {
+    let p = property(u.choose(&STRING_PROPERTIES)?);
+    Ok(match u.int_in_range(0..=2)? {
+        0 => {
+            let Expr::Literal(word) = word(u)? else {
+                unreachable!("words are literals")
+            };
+            operation("like", vec![p, Expr::Literal(format!("{word}%"))])
+        }
+        1 => operation(
+            "=",
+            vec![
+                operation("casei", vec![p]),
+                operation("casei", vec![word(u)?]),
+            ],
+        ),
+        _ => operation(u.choose(&["=", "<>"])?, vec![p, word(u)?]),
+    })
+}
+
+fn geometry(u: &mut Unstructured<'_>) -> Result<Expr> {
+    let (x0, x1) = ordered(u.int_in_range(-180..=180)?, u.int_in_range(-180..=180)?);
+    let (y0, y1) = ordered(u.int_in_range(-90..=90)?, u.int_in_range(-90..=90)?);
+    let (x1, y1) = (x1.max(x0 + 1), y1.max(y0 + 1));
+    let wkt = match u.int_in_range(0..=3)? {
+        0 => format!("POINT({x0} {y0})"),
+        1 => format!("LINESTRING({x0} {y0},{x1} {y1})"),
+        2 => format!("POLYGON(({x0} {y0},{x1} {y0},{x1} {y1},{x0} {y1},{x0} {y0}))"),
+        _ => {
+            return Ok(Expr::BBox {
+                bbox: [x0, y0, x1, y1].into_iter().map(number).collect(),
+            })
+        }
+    };
+    Ok(Expr::Geometry(Geometry::Wkt(wkt)))
+}
+
+fn temporal(u: &mut Unstructured<'_>) -> Result<Expr> {
+    let (a, b) = ordered(u.int_in_range(0..=20_000)?, u.int_in_range(0..=20_000)?);
+    let (a, b) = (date(a), date(b));
+    Ok(match u.int_in_range(0..=2)? {
+        0 => Expr::Date {
+            date: Box::new(Expr::Literal(a)),
+        },
+        1 => Expr::Timestamp {
+            timestamp: Box::new(Expr::Literal(format!("{a}T00:00:00Z"))),
+        },
+        _ => Expr::Interval {
+            interval: vec![Expr::Literal(a), Expr::Literal(b)],
+        },
+    })
+}
+
+/// Returns the date `days` days after 1970-01-01.
+fn date(days: i32) -> String {
+    let epoch = jiff::civil::date(1970, 1, 1);
+    epoch
+        .checked_add(jiff::Span::new().days(days))
+        .unwrap_or(epoch)
+        .to_string()
+}
+
+fn word(u: &mut Unstructured<'_>) -> Result<Expr> {
+    Ok(Expr::Literal(u.choose(&WORDS)?.to_string()))
+}
+
+fn number(v: i32) -> Expr {
+    Expr::Float(v.into())
+}
+
+fn property(name: &str) -> Expr {
+    Expr::Property {
+        property: name.to_string(),
+    }
+}
+
+fn operation(op: &'static str, args: Vec<Expr>) -> Expr {
+    Expr::Operation {
+        op: op.into(),
+        args,
+    }
+}
+
+fn ordered(a: i32, b: i32) -> (i32, i32) {
+    (a.min(b), a.max(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExprClass;
+    use crate::Expr;
+    use arbitrary::Unstructured;
+
+    #[test]
+    fn generated_expressions_are_valid() {
+        let mut state: u64 = 42;
+        let bytes: Vec<u8> = (0..1 << 16)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 33) as u8
+            })
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..200 {
+            let expr = Expr::generate(&mut u, &ExprClass::ALL).unwrap();
+            assert!(expr.is_valid(), "{}", expr.to_text().unwrap());
+            let roundtrip: Expr = expr.to_text().unwrap().parse().unwrap();
+            assert!(roundtrip.is_valid(), "{}", expr.to_text().unwrap());
+        }
+    }
+}
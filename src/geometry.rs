@@ -1,21 +1,70 @@
-use crate::Error;
+use crate::{expr::float_bits, Error};
+use geo::BoundingRect;
+use geo_types::Rect;
 use geozero::{wkt::Wkt, CoordDimensions, ToGeo, ToWkt};
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+};
 
 const DEFAULT_NDIM: usize = 2;
 
 /// Crate-specific geometry type to hold either WKT or GeoJSON.
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(untagged)]
+///
+/// Both kinds serialize as GeoJSON. Deserialization reads GeoJSON, picking
+/// the shape of `coordinates` from the `type` member.
+#[derive(Debug, Clone)]
 pub enum Geometry {
     /// A GeoJSON geometry.
     GeoJSON(geojson::Geometry),
 
     /// A WKT geometry.
-    #[serde(skip_deserializing, serialize_with = "to_geojson")]
     Wkt(String),
 }
 
+/// The members of a GeoJSON geometry object, read straight into typed
+/// coordinates instead of through a [Value].
+#[derive(Deserialize)]
+struct RawGeometry {
+    #[serde(flatten)]
+    value: RawValue,
+    bbox: Option<Vec<f64>>,
+    #[serde(flatten)]
+    foreign_members: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum RawValue {
+    Point {
+        coordinates: Vec<f64>,
+    },
+    MultiPoint {
+        coordinates: Vec<Vec<f64>>,
+    },
+    LineString {
+        coordinates: Vec<Vec<f64>>,
+    },
+    MultiLineString {
+        coordinates: Vec<Vec<Vec<f64>>>,
+    },
+    Polygon {
+        coordinates: Vec<Vec<Vec<f64>>>,
+    },
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<Vec<f64>>>>,
+    },
+    GeometryCollection {
+        geometries: Vec<RawGeometry>,
+    },
+}
+
+/// Serializes a GeoJSON geometry member by member, without building an
+/// intermediate [Value] like [geojson::Geometry]'s own implementation does.
+struct GeoJsonRef<'a>(&'a geojson::Geometry);
+
 impl Geometry {
     /// Converts this geometry to Well-Known Text (WKT).
     ///
@@ -30,29 +79,348 @@ impl Geometry {
     /// assert_eq!("POINT(-105.1019 40.1672)", geometry.to_wkt().unwrap());
     /// ```
     pub fn to_wkt(&self) -> Result<String, Error> {
+        self.wkt().map(Cow::into_owned)
+    }
+
+    /// Returns the WKT of this geometry, borrowing it if it is already WKT.
+    pub(crate) fn wkt(&self) -> Result<Cow<'_, str>, Error> {
         match self {
-            Geometry::Wkt(wkt) => Ok(wkt.clone()),
+            Geometry::Wkt(wkt) => Ok(Cow::Borrowed(wkt)),
             Geometry::GeoJSON(geojson) => {
                 let dims = match geojson_ndims(geojson) {
                     3 => CoordDimensions::xyz(),
                     4 => CoordDimensions::xyzm(),
                     _ => CoordDimensions::xy(),
                 };
-                let geometry: geo_types::Geometry<f64> = geojson.clone().try_into()?;
-                geometry.to_wkt_ndim(dims).map_err(Error::from)
+                let geometry = geo_types::Geometry::<f64>::try_from(&geojson.value)?;
+                Ok(Cow::Owned(geometry.to_wkt_ndim(dims)?))
+            }
+        }
+    }
+
+    /// Converts this geometry to Well-Known Text (WKT), rounding coordinates to
+    /// at most `precision` decimal places.
+    ///
+    /// Trailing zeros are dropped, so `1.50` is written as `1.5`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Geometry;
+    ///
+    /// let geometry = Geometry::Wkt("POINT(-105.10193 40.16721)".to_string());
+    /// assert_eq!("POINT(-105.1 40.17)", geometry.to_wkt_with_precision(2).unwrap());
+    /// ```
+    pub fn to_wkt_with_precision(&self, precision: usize) -> Result<String, Error> {
+        let wkt = self.wkt()?;
+        let mut output = String::with_capacity(wkt.len());
+        let mut number = String::new();
+        for c in wkt.chars() {
+            if c.is_ascii_digit()
+                || matches!(c, '.' | '-' | '+')
+                || (matches!(c, 'e' | 'E') && !number.is_empty())
+            {
+                number.push(c);
+            } else {
+                push_rounded(&mut output, &number, precision);
+                number.clear();
+                output.push(c);
+            }
+        }
+        push_rounded(&mut output, &number, precision);
+        Ok(output)
+    }
+
+    /// Converts this geometry to a [geo_types::Geometry].
+    ///
+    /// Any z or m coordinates are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Geometry;
+    ///
+    /// let geometry = Geometry::Wkt("POINT(-105.1019 40.1672)".to_string());
+    /// let geometry = geometry.to_geo().unwrap();
+    /// ```
+    pub fn to_geo(&self) -> Result<geo_types::Geometry, Error> {
+        match self {
+            Geometry::Wkt(wkt) => Wkt(wkt).to_geo().map_err(Error::from),
+            Geometry::GeoJSON(geojson) => {
+                geo_types::Geometry::try_from(&geojson.value).map_err(Error::from)
             }
         }
     }
+
+    /// Returns the two-dimensional bounding box (envelope) of this geometry.
+    ///
+    /// Returns `None` for empty geometries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Geometry;
+    ///
+    /// let geometry = Geometry::Wkt("LINESTRING(-105 40, -104 41)".to_string());
+    /// let rect = geometry.bounding_box().unwrap().unwrap();
+    /// assert_eq!(rect.min().x, -105.);
+    /// assert_eq!(rect.max().y, 41.);
+    /// ```
+    pub fn bounding_box(&self) -> Result<Option<Rect>, Error> {
+        Ok(self.to_geo()?.bounding_rect())
+    }
+
+    /// Returns the envelope of this geometry as a polygon geometry.
+    ///
+    /// Returns `None` for empty geometries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Geometry;
+    ///
+    /// let geometry = Geometry::Wkt("LINESTRING(-105 40, -104 41)".to_string());
+    /// let envelope = geometry.envelope().unwrap().unwrap();
+    /// assert_eq!(
+    ///     envelope.to_wkt().unwrap(),
+    ///     "POLYGON((-105 40,-105 41,-104 41,-104 40,-105 40))"
+    /// );
+    /// ```
+    pub fn envelope(&self) -> Result<Option<Geometry>, Error> {
+        Ok(self.bounding_box()?.map(Geometry::from))
+    }
+}
+
+impl PartialEq for Geometry {
+    fn eq(&self, other: &Geometry) -> bool {
+        match (self, other) {
+            (Geometry::Wkt(wkt), Geometry::Wkt(other)) => wkt == other,
+            (Geometry::GeoJSON(geojson), Geometry::GeoJSON(other)) => geojson.key_eq(other),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Geometry {}
+
+impl Hash for Geometry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Geometry::Wkt(wkt) => wkt.hash(state),
+            Geometry::GeoJSON(geojson) => geojson.key_hash(state),
+        }
+    }
 }
 
-fn to_geojson<S>(wkt: &String, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    use serde::ser::Error;
+/// Equality and hashing for GeoJSON, with coordinates compared like
+/// [Expr::Float](crate::Expr::Float) numbers.
+trait GeoJsonKey {
+    fn key_eq(&self, other: &Self) -> bool;
 
-    let geometry = Wkt(wkt).to_geo().map_err(Error::custom)?;
-    geojson::ser::serialize_geometry(&geometry, serializer)
+    fn key_hash<H: Hasher>(&self, state: &mut H);
+}
+
+impl GeoJsonKey for f64 {
+    fn key_eq(&self, other: &f64) -> bool {
+        float_bits(*self) == float_bits(*other)
+    }
+
+    fn key_hash<H: Hasher>(&self, state: &mut H) {
+        float_bits(*self).hash(state)
+    }
+}
+
+impl<T: GeoJsonKey> GeoJsonKey for Vec<T> {
+    fn key_eq(&self, other: &Vec<T>) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.key_eq(b))
+    }
+
+    fn key_hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for value in self {
+            value.key_hash(state);
+        }
+    }
+}
+
+impl<T: GeoJsonKey> GeoJsonKey for Option<T> {
+    fn key_eq(&self, other: &Option<T>) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.key_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn key_hash<H: Hasher>(&self, state: &mut H) {
+        self.is_some().hash(state);
+        if let Some(value) = self {
+            value.key_hash(state);
+        }
+    }
+}
+
+impl GeoJsonKey for geojson::Geometry {
+    fn key_eq(&self, other: &geojson::Geometry) -> bool {
+        self.bbox.key_eq(&other.bbox)
+            && self.value.key_eq(&other.value)
+            && self.foreign_members == other.foreign_members
+    }
+
+    fn key_hash<H: Hasher>(&self, state: &mut H) {
+        self.bbox.key_hash(state);
+        self.value.key_hash(state);
+        // JSON values aren't hashable, and equal maps can have their members
+        // in different orders, so only the number of members is hashed.
+        self.foreign_members.as_ref().map(Map::len).hash(state);
+    }
+}
+
+impl GeoJsonKey for geojson::Value {
+    fn key_eq(&self, other: &geojson::Value) -> bool {
+        use geojson::Value;
+
+        match (self, other) {
+            (Value::Point(a), Value::Point(b)) => a.key_eq(b),
+            (Value::MultiPoint(a), Value::MultiPoint(b)) => a.key_eq(b),
+            (Value::LineString(a), Value::LineString(b)) => a.key_eq(b),
+            (Value::MultiLineString(a), Value::MultiLineString(b)) => a.key_eq(b),
+            (Value::Polygon(a), Value::Polygon(b)) => a.key_eq(b),
+            (Value::MultiPolygon(a), Value::MultiPolygon(b)) => a.key_eq(b),
+            (Value::GeometryCollection(a), Value::GeometryCollection(b)) => a.key_eq(b),
+            _ => false,
+        }
+    }
+
+    fn key_hash<H: Hasher>(&self, state: &mut H) {
+        use geojson::Value;
+
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Point(coordinates) => coordinates.key_hash(state),
+            Value::MultiPoint(coordinates) | Value::LineString(coordinates) => {
+                coordinates.key_hash(state)
+            }
+            Value::MultiLineString(coordinates) | Value::Polygon(coordinates) => {
+                coordinates.key_hash(state)
+            }
+            Value::MultiPolygon(coordinates) => coordinates.key_hash(state),
+            Value::GeometryCollection(geometries) => geometries.key_hash(state),
+        }
+    }
+}
+
+impl From<Rect> for Geometry {
+    fn from(rect: Rect) -> Geometry {
+        let polygon = geo_types::Geometry::Polygon(rect.to_polygon());
+        Geometry::GeoJSON(geojson::Geometry::new((&polygon).into()))
+    }
+}
+
+/// Pushes a WKT number token onto the output, rounded to `precision` decimal
+/// places. Tokens that aren't numbers are pushed unchanged.
+fn push_rounded(output: &mut String, token: &str, precision: usize) {
+    let Ok(value) = token.parse::<f64>() else {
+        output.push_str(token);
+        return;
+    };
+    let mut s = format!("{value:.precision$}");
+    if s.contains('.') {
+        s.truncate(s.trim_end_matches('0').trim_end_matches('.').len());
+    }
+    if s == "-0" {
+        s = "0".to_string();
+    }
+    output.push_str(&s);
+}
+
+impl Serialize for Geometry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::Error;
+
+        match self {
+            Geometry::GeoJSON(geojson) => GeoJsonRef(geojson).serialize(serializer),
+            Geometry::Wkt(wkt) => {
+                let geometry = Wkt(wkt).to_geo().map_err(Error::custom)?;
+                GeoJsonRef(&geojson::Geometry::new((&geometry).into())).serialize(serializer)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Geometry {
+    fn deserialize<D>(deserializer: D) -> Result<Geometry, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RawGeometry::deserialize(deserializer).map(|raw| Geometry::GeoJSON(raw.into()))
+    }
+}
+
+impl From<RawGeometry> for geojson::Geometry {
+    fn from(raw: RawGeometry) -> geojson::Geometry {
+        use geojson::Value;
+
+        let value = match raw.value {
+            RawValue::Point { coordinates } => Value::Point(coordinates),
+            RawValue::MultiPoint { coordinates } => Value::MultiPoint(coordinates),
+            RawValue::LineString { coordinates } => Value::LineString(coordinates),
+            RawValue::MultiLineString { coordinates } => Value::MultiLineString(coordinates),
+            RawValue::Polygon { coordinates } => Value::Polygon(coordinates),
+            RawValue::MultiPolygon { coordinates } => Value::MultiPolygon(coordinates),
+            RawValue::GeometryCollection { geometries } => {
+                Value::GeometryCollection(geometries.into_iter().map(Into::into).collect())
+            }
+        };
+        // Flattened maps also see the members that `value` consumed.
+        let mut foreign_members = raw.foreign_members;
+        for key in ["type", "coordinates", "geometries"] {
+            let _ = foreign_members.remove(key);
+        }
+        geojson::Geometry {
+            bbox: raw.bbox,
+            value,
+            foreign_members: Some(foreign_members).filter(|members| !members.is_empty()),
+        }
+    }
+}
+
+impl Serialize for GeoJsonRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use geojson::Value::*;
+
+        let geometry = self.0;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", geometry.value.type_name())?;
+        match &geometry.value {
+            Point(coordinates) => map.serialize_entry("coordinates", coordinates)?,
+            MultiPoint(coordinates) | LineString(coordinates) => {
+                map.serialize_entry("coordinates", coordinates)?
+            }
+            MultiLineString(coordinates) | Polygon(coordinates) => {
+                map.serialize_entry("coordinates", coordinates)?
+            }
+            MultiPolygon(coordinates) => map.serialize_entry("coordinates", coordinates)?,
+            GeometryCollection(geometries) => map.serialize_entry(
+                "geometries",
+                &geometries.iter().map(GeoJsonRef).collect::<Vec<_>>(),
+            )?,
+        }
+        if let Some(bbox) = &geometry.bbox {
+            map.serialize_entry("bbox", bbox)?;
+        }
+        for (key, value) in geometry.foreign_members.iter().flatten() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
 }
 
 fn geojson_ndims(geojson: &geojson::Geometry) -> usize {
@@ -80,3 +448,56 @@ fn geojson_ndims(geojson: &geojson::Geometry) -> usize {
         GeometryCollection(v) => v.first().map(geojson_ndims).unwrap_or(DEFAULT_NDIM),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Geometry;
+    use std::collections::HashSet;
+
+    #[test]
+    fn geojson_roundtrip() {
+        for json in [
+            r#"{"type":"Point","coordinates":[-105.1019,40.1672]}"#,
+            r#"{"type":"Polygon","coordinates":[[[0.0,0.0],[1.0,0.0],[1.0,1.0],[0.0,0.0]]],"bbox":[0.0,0.0,1.0,1.0],"foo":"bar"}"#,
+            r#"{"type":"GeometryCollection","geometries":[{"type":"Point","coordinates":[1.0,2.0,3.0]},{"type":"MultiPoint","coordinates":[]}]}"#,
+        ] {
+            let geometry: Geometry = serde_json::from_str(json).unwrap();
+            assert_eq!(serde_json::to_string(&geometry).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn type_after_coordinates() {
+        let geometry: Geometry =
+            serde_json::from_str(r#"{"coordinates":[[0,0],[1,1]],"type":"LineString"}"#).unwrap();
+        assert_eq!(geometry.to_wkt().unwrap(), "LINESTRING(0 0,1 1)");
+    }
+
+    #[test]
+    fn invalid() {
+        for json in [
+            r#"{"type":"Point","coordinates":[[0,0]]}"#,
+            r#"{"type":"Circle","coordinates":[0,0]}"#,
+            r#"{"coordinates":[0,0]}"#,
+        ] {
+            assert!(serde_json::from_str::<Geometry>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn eq_and_hash() {
+        let point =
+            |x: f64| Geometry::GeoJSON(geojson::Geometry::new(geojson::Value::Point(vec![x, 0.])));
+        assert_eq!(point(0.), point(-0.));
+        assert_eq!(point(f64::NAN), point(f64::NAN));
+        assert_ne!(point(f64::NAN), point(0.));
+        let a: Geometry =
+            serde_json::from_str(r#"{"type":"Point","coordinates":[0,0],"a":1,"b":2}"#).unwrap();
+        let b: Geometry =
+            serde_json::from_str(r#"{"type":"Point","coordinates":[0,0],"b":2,"a":1}"#).unwrap();
+        let c: Geometry =
+            serde_json::from_str(r#"{"type":"LineString","coordinates":[[0,0],[1,1]]}"#).unwrap();
+        let set: HashSet<Geometry> = [point(-0.), point(0.), a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 3);
+    }
+}
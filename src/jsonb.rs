@@ -0,0 +1,178 @@
+use crate::{type_check::infer, Expr, JsonbOptions, Queryables, Type};
+use pg_escape::quote_literal;
+
+impl JsonbOptions {
+    /// Returns the SQL accessor for a property, cast for its type.
+    pub(crate) fn accessor(&self, property: &str, property_type: Option<Type>) -> String {
+        let path: Vec<String> = self
+            .prefix
+            .iter()
+            .cloned()
+            .chain(json_path(property))
+            .map(|key| quote_array_element(&key))
+            .collect();
+        let path = quote_literal(&format!("{{{}}}", path.join(",")));
+        let column = &self.column;
+        match property_type {
+            Some(Type::Number) => format!("({column} #>> {path})::numeric"),
+            Some(Type::Boolean) => format!("({column} #>> {path})::boolean"),
+            Some(Type::Temporal) => format!("({column} #>> {path})::timestamptz"),
+            Some(Type::Geometry) => format!("ST_GeomFromGeoJSON({column} #> {path})"),
+            Some(Type::Array) | Some(Type::Object) => format!("({column} #> {path})"),
+            Some(Type::String) | Some(Type::Null) | None => format!("({column} #>> {path})"),
+        }
+    }
+}
+
+/// Returns the type that properties among an operation's arguments are
+/// compared with, so that JSONB accessors can be cast to match.
+pub(crate) fn type_hint(op: &str, args: &[Expr], queryables: &Queryables) -> Option<Type> {
+    if op.starts_with("t_") || op == "anyinteracts" {
+        return Some(Type::Temporal);
+    } else if op.starts_with("s_") {
+        return Some(Type::Geometry);
+    } else if op.starts_with("a_") {
+        return Some(Type::Array);
+    }
+    let compares_values = matches!(op, "=" | "<>" | "<" | "<=" | ">" | ">=" | "between" | "in")
+        || matches!(op, "+" | "-" | "*" | "/" | "%" | "^" | "div");
+    if !compares_values {
+        return None;
+    }
+    args.iter()
+        .flat_map(|arg| match arg {
+            // The list of `in`.
+            Expr::Array(array) => array.iter().collect(),
+            arg => vec![arg],
+        })
+        .filter(|arg| !matches!(arg, Expr::Property { .. }))
+        .find_map(|arg| infer(arg, queryables).filter(|t| *t != Type::Null))
+}
+
+/// Splits a property into the keys of its JSON path, e.g. `bands[0].name`
+/// into `bands`, `0`, and `name`.
+///
+/// A segment whose brackets don't hold an index is kept as a single key.
+fn json_path(property: &str) -> Vec<String> {
+    let mut path = Vec::new();
+    for segment in property.split('.') {
+        let Some((key, indices)) = segment.split_once('[') else {
+            path.push(segment.to_string());
+            continue;
+        };
+        let indices: Option<Vec<&str>> = indices
+            .strip_suffix(']')
+            .map(|indices| indices.split("][").collect());
+        match indices {
+            Some(indices)
+                if !key.is_empty()
+                    && indices
+                        .iter()
+                        .all(|i| !i.is_empty() && i.bytes().all(|b| b.is_ascii_digit())) =>
+            {
+                path.push(key.to_string());
+                path.extend(indices.into_iter().map(String::from));
+            }
+            _ => path.push(segment.to_string()),
+        }
+    }
+    path
+}
+
+/// Quotes an element of a PostgreSQL text array literal, if needed.
+fn quote_array_element(element: &str) -> String {
+    let needs_quotes = element.is_empty()
+        || element.eq_ignore_ascii_case("null")
+        || element
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '{' | '}' | ',' | '"' | '\\'));
+    if needs_quotes {
+        format!("\"{}\"", element.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        element.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expr, TemporalFormat, ToSqlOptions};
+
+    fn sql(s: &str) -> String {
+        let options = ToSqlOptions::default().with_jsonb("content", ["properties"]);
+        let expr: Expr = s.parse().unwrap();
+        expr.to_sql_with_options(&options).unwrap().query
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            sql("assets.thumbnail.href = 'a'"),
+            "((content #>> '{properties,assets,thumbnail,href}') = $1)"
+        );
+        assert_eq!(
+            sql(r#""grid[1][2]" > 3"#),
+            "((content #>> '{properties,grid,1,2}')::numeric > $1)"
+        );
+        assert_eq!(
+            sql(r#""a b[x]" = 'c'"#),
+            "((content #>> '{properties,\"a b[x]\"}') = $1)"
+        );
+        assert_eq!(
+            sql(r#""it's" = 'c'"#),
+            "((content #>> '{properties,it''s}') = $1)"
+        );
+    }
+
+    #[test]
+    fn casts_from_usage() {
+        assert_eq!(
+            sql("a IN (1, 2)"),
+            "in((content #>> '{properties,a}')::numeric, [$1, $2])"
+        );
+        assert_eq!(
+            sql("a BETWEEN 1 AND 2"),
+            "((content #>> '{properties,a}')::numeric BETWEEN $1 AND $2)"
+        );
+        assert_eq!(
+            sql("t_after(updated, TIMESTAMP('2020-01-01T00:00:00Z'))"),
+            "t_after((content #>> '{properties,updated}')::timestamptz, $1::timestamptz)"
+        );
+        assert_eq!(
+            sql("s_intersects(geometry, POINT(0 0))"),
+            "s_intersects(ST_GeomFromGeoJSON(content #> '{properties,geometry}'), $1)"
+        );
+        assert_eq!(
+            sql("a LIKE 'b%' AND c IS NULL"),
+            "(((content #>> '{properties,a}') LIKE $1 ESCAPE '\\') \
+            AND ((content #>> '{properties,c}') IS NULL))"
+        );
+    }
+
+    #[test]
+    fn temporal_ranges() {
+        let mut options = ToSqlOptions::default().with_jsonb("content", ["properties"]);
+        options.temporal_format = TemporalFormat::Range;
+        let expr: Expr = "t_intersects(datetime, INTERVAL(start_datetime, '..'))"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            "(TSTZRANGE((content #>> '{properties,datetime}')::timestamptz,\
+            (content #>> '{properties,datetime}')::timestamptz,'[]') \
+            && TSTZRANGE((content #>> '{properties,start_datetime}')::timestamptz,NULL,'[)'))"
+        );
+    }
+
+    #[test]
+    fn columns_take_precedence() {
+        let options = ToSqlOptions::default()
+            .with_jsonb("content", Vec::<String>::new())
+            .with_json(r#"{"id": "id"}"#)
+            .unwrap();
+        let expr: Expr = "id = 'a' AND collection = 'b'".parse().unwrap();
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            "((id = $1) AND ((content #>> '{collection}') = $2))"
+        );
+    }
+}
@@ -30,19 +30,48 @@
 )]
 #![allow(clippy::result_large_err)]
 
+mod canonical;
 mod error;
 mod expr;
+mod extent;
+mod filter_lang;
+#[cfg(feature = "arbitrary")]
+mod generate;
 mod geometry;
+mod jsonb;
+mod lint;
+mod operator;
 mod parser;
+mod pruning;
+mod queryables;
+mod redact;
+mod schema;
+mod source_map;
+mod split;
+mod temporal;
+mod tree;
+mod type_check;
 mod validator;
 
 pub use error::Error;
 pub use expr::Expr;
+pub use extent::Extent;
+pub use filter_lang::{FilterLang, FilterParams, CRS84};
+#[cfg(feature = "arbitrary")]
+pub use generate::ExprClass;
 pub use geometry::Geometry;
-pub use parser::parse_text;
+pub use lint::LintWarning;
+pub use operator::{OperatorPlugin, Operators};
+pub use parser::{parse_text, parse_text_with_max_depth, parse_text_with_source_map};
+pub use pruning::{ColumnPredicate, ComparisonOp, Pruning, Scalar};
+pub use queryables::{Queryables, Type};
 use serde_derive::{Deserialize, Serialize};
-use std::{fs, path::Path};
-pub use validator::Validator;
+pub use source_map::SourceMap;
+pub use split::Split;
+use std::{collections::HashMap, fs, path::Path};
+pub use temporal::DateRange;
+pub use type_check::TypeError;
+pub use validator::{ValidationIssue, ValidationReport, Validator};
 
 /// A SQL query, broken into the query and parameters.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -54,6 +83,303 @@ pub struct SqlQuery {
     pub params: Vec<String>,
 }
 
+/// Options for [Expr::to_sql_with_options].
+///
+/// # Examples
+///
+/// ```
+/// use cql2::{GeometryFormat, ToSqlOptions};
+///
+/// let options = ToSqlOptions {
+///     precision: Some(6),
+///     srid: 3857,
+///     geometry_format: GeometryFormat::Ewkt,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToSqlOptions {
+    /// The number of decimal places to round geometry coordinates to.
+    ///
+    /// If `None`, coordinates are written with full precision.
+    pub precision: Option<usize>,
+
+    /// The spatial reference identifier of geometry literals.
+    ///
+    /// Defaults to 4326 (WGS 84), the only reference system CQL2 geometries
+    /// use without the filter-crs extension.
+    pub srid: u32,
+
+    /// How geometry literals are written.
+    pub geometry_format: GeometryFormat,
+
+    /// How temporal operators are written.
+    pub temporal_format: TemporalFormat,
+
+    /// SQL expressions to use for properties, keyed by property name.
+    ///
+    /// The expressions are written as-is, so they must be trusted. Properties
+    /// that aren't in the map are written as quoted identifiers.
+    pub columns: HashMap<String, String>,
+
+    /// Whether to write values into the query as SQL literals instead of
+    /// parameters.
+    ///
+    /// Strings are single-quoted with embedded quotes doubled, and
+    /// [SqlQuery::params] is empty. Prefer parameters when the database
+    /// driver supports them.
+    pub inline_params: bool,
+
+    /// The deepest nesting allowed, see [Expr::depth].
+    ///
+    /// Deeper expressions return [Error::MaxDepthExceeded] instead of risking
+    /// a stack overflow.
+    pub max_depth: usize,
+
+    /// Queryables to map properties to SQL, see [Queryables::sql_column].
+    ///
+    /// Properties in [columns](ToSqlOptions::columns) take precedence. If set,
+    /// any other property that isn't a queryable returns
+    /// [Error::UnknownProperty].
+    pub queryables: Option<Queryables>,
+
+    /// Writes properties as accessors into a JSONB column, instead of as
+    /// quoted identifiers.
+    ///
+    /// Properties in [columns](ToSqlOptions::columns), and queryables with an
+    /// `x-column`, take precedence.
+    pub jsonb: Option<JsonbOptions>,
+
+    /// Plugins that write custom functions as SQL.
+    ///
+    /// Functions without a plugin are written as calls to a SQL function of
+    /// the same name.
+    pub operators: Operators,
+}
+
+/// How [Expr::to_sql_with_options] writes properties as JSONB accessors, see
+/// [ToSqlOptions::jsonb].
+///
+/// A property is a path into the document: dots separate keys and brackets
+/// hold array indices, so `bands[0].common_name` is the `common_name` of the
+/// first band. In cql2-text, properties with brackets must be double quoted.
+///
+/// Accessors are cast according to the property's queryable type or, without
+/// one, the type of the values it is compared with: numbers to `numeric`,
+/// booleans to `boolean`, temporal values to `timestamptz`, and geometries
+/// with `ST_GeomFromGeoJSON`. Arrays and objects are left as `jsonb`, and
+/// anything else is text.
+///
+/// # Examples
+///
+/// ```
+/// use cql2::{Expr, ToSqlOptions};
+///
+/// let options = ToSqlOptions::default().with_jsonb("content", ["properties"]);
+/// let expr: Expr = r#"eo:cloud_cover < 10 AND "bands[0].common_name" = 'red'"#.parse().unwrap();
+/// let sql = expr.to_sql_with_options(&options).unwrap();
+/// assert_eq!(
+///     sql.query,
+///     "(((content #>> '{properties,eo:cloud_cover}')::numeric < $1) \
+///     AND ((content #>> '{properties,bands,0,common_name}') = $2))"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonbOptions {
+    /// The SQL expression for the JSONB column, e.g. `content`.
+    ///
+    /// The expression is written as-is, so it must be trusted.
+    pub column: String,
+
+    /// The keys that properties are nested under, e.g. `["properties"]` for
+    /// STAC items.
+    pub prefix: Vec<String>,
+}
+
+/// How [Expr::to_sql_with_options] writes geometry literals.
+///
+/// In every format the geometry is passed as a parameter. The examples show
+/// the parameter for `POINT(0 0)` with an SRID of 4326.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GeometryFormat {
+    /// WKT prefixed with the EPSG code, e.g. `EPSG:4326;POINT(0 0)`.
+    #[default]
+    EpsgWkt,
+
+    /// Extended WKT, e.g. `SRID=4326;POINT(0 0)`.
+    Ewkt,
+
+    /// Plain WKT, e.g. `POINT(0 0)`, without any SRID.
+    Wkt,
+
+    /// Plain WKT, with the placeholder wrapped in `ST_SetSRID`, e.g.
+    /// `ST_SetSRID(ST_GeomFromText($1), 4326)`.
+    SetSrid,
+}
+
+/// How [Expr::to_sql_with_options] writes temporal operators.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemporalFormat {
+    /// Function calls named after the operator, e.g.
+    /// `t_intersects("datetime", TSTZRANGE($1,$2))`.
+    #[default]
+    Function,
+
+    /// PostgreSQL range operators, e.g.
+    /// `(TSTZRANGE("datetime","datetime",'[]') && TSTZRANGE($1,$2,'[]'))`,
+    /// which can use GiST range indexes.
+    ///
+    /// Only `t_intersects` (`&&`), `t_equals` (`=`), `t_before` (`<<`),
+    /// `t_after` (`>>`), and `t_disjoint` (`NOT &&`) have range operators
+    /// with the same semantics; other temporal operators are still written
+    /// as function calls.
+    Range,
+}
+
+impl Default for ToSqlOptions {
+    fn default() -> ToSqlOptions {
+        ToSqlOptions {
+            precision: None,
+            srid: 4326,
+            geometry_format: GeometryFormat::default(),
+            temporal_format: TemporalFormat::default(),
+            columns: HashMap::new(),
+            inline_params: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            queryables: None,
+            jsonb: None,
+            operators: Operators::new(),
+        }
+    }
+}
+
+impl ToSqlOptions {
+    /// Adds property-to-SQL mappings from a JSON object to
+    /// [columns](ToSqlOptions::columns).
+    ///
+    /// The object's keys are property names and its values are SQL
+    /// expressions, which replace any existing mappings for the same
+    /// properties.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, ToSqlOptions};
+    ///
+    /// let options = ToSqlOptions::default()
+    ///     .with_json(r#"{"eo:cloud_cover": "(properties->>'eo:cloud_cover')::float"}"#)
+    ///     .unwrap();
+    /// let expr: Expr = "eo:cloud_cover < 10".parse().unwrap();
+    /// let sql = expr.to_sql_with_options(&options).unwrap();
+    /// assert_eq!(sql.query, "((properties->>'eo:cloud_cover')::float < $1)");
+    /// ```
+    pub fn with_json(mut self, s: &str) -> Result<ToSqlOptions, Error> {
+        let columns: HashMap<String, String> = serde_json::from_str(s)?;
+        self.columns.extend(columns);
+        Ok(self)
+    }
+
+    /// Sets the [queryables](ToSqlOptions::queryables) used to map
+    /// properties to SQL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Queryables, ToSqlOptions};
+    ///
+    /// let queryables: Queryables = r#"{"properties": {
+    ///     "eo:cloud_cover": {"type": "number", "x-column": "properties->>'eo:cloud_cover'"}
+    /// }}"#.parse().unwrap();
+    /// let options = ToSqlOptions::default().with_queryables(queryables);
+    /// let expr: Expr = "eo:cloud_cover < 10".parse().unwrap();
+    /// let sql = expr.to_sql_with_options(&options).unwrap();
+    /// assert_eq!(sql.query, "((properties->>'eo:cloud_cover')::numeric < $1)");
+    ///
+    /// let expr: Expr = "platform = 'landsat-8'".parse().unwrap();
+    /// assert!(expr.to_sql_with_options(&options).is_err());
+    /// ```
+    pub fn with_queryables(mut self, queryables: Queryables) -> ToSqlOptions {
+        self.queryables = Some(queryables);
+        self
+    }
+
+    /// Writes properties as accessors into a JSONB column, nested under
+    /// `prefix`, see [JsonbOptions].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Queryables, ToSqlOptions};
+    ///
+    /// let queryables: Queryables = r#"{"properties": {"datetime": {"type": "string", "format": "date-time"}}}"#
+    ///     .parse()
+    ///     .unwrap();
+    /// let options = ToSqlOptions::default()
+    ///     .with_jsonb("content", ["properties"])
+    ///     .with_queryables(queryables);
+    /// let expr: Expr = "datetime > TIMESTAMP('2020-01-01T00:00:00Z')".parse().unwrap();
+    /// let sql = expr.to_sql_with_options(&options).unwrap();
+    /// assert_eq!(
+    ///     sql.query,
+    ///     "((content #>> '{properties,datetime}')::timestamptz > $1::timestamptz)"
+    /// );
+    /// ```
+    pub fn with_jsonb(
+        mut self,
+        column: impl Into<String>,
+        prefix: impl IntoIterator<Item = impl Into<String>>,
+    ) -> ToSqlOptions {
+        self.jsonb = Some(JsonbOptions {
+            column: column.into(),
+            prefix: prefix.into_iter().map(Into::into).collect(),
+        });
+        self
+    }
+
+    /// Registers a plugin in [operators](ToSqlOptions::operators).
+    ///
+    /// Returns [Error::BuiltinOperator] if the plugin is named after a
+    /// built-in operator, see [Operators::register].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Error, Expr, OperatorPlugin, ToSqlOptions};
+    ///
+    /// #[derive(Debug)]
+    /// struct InCatalog;
+    ///
+    /// impl OperatorPlugin for InCatalog {
+    ///     fn name(&self) -> &str {
+    ///         "in_catalog"
+    ///     }
+    ///
+    ///     fn to_sql(&self, args: &[String]) -> Result<String, Error> {
+    ///         Ok(format!("(collection = ANY({}))", args.join(", ")))
+    ///     }
+    /// }
+    ///
+    /// let options = ToSqlOptions::default().with_operator(InCatalog).unwrap();
+    /// let expr: Expr = "in_catalog('landsat')".parse().unwrap();
+    /// let sql = expr.to_sql_with_options(&options).unwrap();
+    /// assert_eq!(sql.query, "(collection = ANY($1))");
+    /// ```
+    pub fn with_operator(
+        mut self,
+        plugin: impl OperatorPlugin + 'static,
+    ) -> Result<ToSqlOptions, Error> {
+        self.operators.register(plugin)?;
+        Ok(self)
+    }
+}
+
+/// The default for the deepest nesting of an expression, see [Expr::depth].
+///
+/// cql2-text parsing, validation, and conversion to text and SQL reject
+/// deeper expressions, because they recurse and could overflow the stack.
+/// cql2-json parsing is limited by [serde_json]'s own recursion limit.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 /// Parses a cql2-json string into a CQL2 expression.
 ///
 /// # Examples
@@ -66,6 +392,22 @@ pub fn parse_json(s: &str) -> Result<Expr, serde_json::Error> {
     serde_json::from_str(s)
 }
 
+/// Parses a CBOR-encoded CQL2 expression, as produced by [Expr::to_cbor].
+///
+/// # Examples
+///
+/// ```
+/// use cql2::Expr;
+///
+/// let expr: Expr = "landsat:scene_id = 'LC82030282019133LGN00'".parse().unwrap();
+/// let bytes = expr.to_cbor().unwrap();
+/// let expr = cql2::parse_cbor(&bytes).unwrap();
+/// ```
+#[cfg(feature = "cbor")]
+pub fn parse_cbor(bytes: &[u8]) -> Result<Expr, Error> {
+    ciborium::from_reader(bytes).map_err(Error::from)
+}
+
 /// Reads a file and returns its contents as a CQL2 expression;
 ///
 /// # Examples
@@ -79,7 +421,7 @@ pub fn parse_file(path: impl AsRef<Path>) -> Result<Expr, Error> {
 }
 
 #[cfg(test)]
-use {assert_json_diff as _, rstest as _};
+use {assert_json_diff as _, criterion as _, rstest as _};
 
 // From https://github.com/rust-lang/cargo/issues/383#issuecomment-720873790,
 // may they be forever blessed.
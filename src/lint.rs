@@ -0,0 +1,228 @@
+use crate::{expr::COMPARISON_OPS, Expr};
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+};
+
+/// A non-fatal problem found by [Expr::lint].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// A JSON pointer to the offending part of the cql2-json representation of
+    /// the expression.
+    pub pointer: String,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at '{}': {}", self.pointer, self.message)
+    }
+}
+
+impl Expr {
+    /// Looks for parts of this expression that are valid but probably not
+    /// what the author meant.
+    ///
+    /// The warnings are:
+    ///
+    /// - A property compared with a string literal that looks like a number,
+    ///   e.g. `eo:cloud_cover < '10'`.
+    /// - Comparisons that are always true or always false, e.g. `1 = 2`, and
+    ///   `true` or `false` in an `AND` or `OR`. A property compared with
+    ///   itself, e.g. `platform = platform`, is only true if it isn't null.
+    /// - The same clause repeated in an `AND` or `OR`.
+    /// - `CASEI` or `ACCENTI` applied to something that isn't a string.
+    ///
+    /// Returns an empty vector if nothing was found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 10".parse().unwrap();
+    /// assert!(expr.lint().is_empty());
+    ///
+    /// let expr: Expr = "eo:cloud_cover < '10' AND platform = 'landsat-8' AND platform = 'landsat-8'"
+    ///     .parse()
+    ///     .unwrap();
+    /// let warnings = expr.lint();
+    /// assert_eq!(warnings.len(), 2);
+    /// assert_eq!(warnings[0].pointer, "/args/0/args/1");
+    /// assert_eq!(warnings[1].message, "duplicate of the clause at '/args/1'");
+    /// ```
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        lint(self, "", &mut warnings);
+        warnings
+    }
+}
+
+fn lint(expr: &Expr, pointer: &str, warnings: &mut Vec<LintWarning>) {
+    let Expr::Operation { op, args } = expr else {
+        return;
+    };
+    for (i, arg) in args.iter().enumerate() {
+        lint(arg, &format!("{pointer}/args/{i}"), warnings);
+    }
+    let mut warn =
+        |pointer: String, message: String| warnings.push(LintWarning { pointer, message });
+    match op.as_ref() {
+        "and" | "or" => {
+            for (i, arg) in args.iter().enumerate() {
+                if let Expr::Bool(v) = arg {
+                    let message = if *v == (op == "or") {
+                        format!("{v} makes the whole {} always {v}", op.to_uppercase())
+                    } else {
+                        format!("{v} has no effect in {}", op.to_uppercase())
+                    };
+                    warn(format!("{pointer}/args/{i}"), message);
+                }
+                if let Some(j) = args[..i].iter().position(|other| other == arg) {
+                    warn(
+                        format!("{pointer}/args/{i}"),
+                        format!("duplicate of the clause at '{pointer}/args/{j}'"),
+                    );
+                }
+            }
+        }
+        "casei" | "accenti" => {
+            for (i, arg) in args.iter().enumerate() {
+                if !could_be_string(arg) {
+                    warn(
+                        format!("{pointer}/args/{i}"),
+                        format!("{op} is applied to something that isn't a string"),
+                    );
+                }
+            }
+        }
+        op if COMPARISON_OPS.contains(&op) && args.len() == 2 => {
+            if let Some(v) = constant_comparison(op, &args[0], &args[1]) {
+                // A comparison with a null property is unknown, so comparing
+                // a property with itself filters out nulls.
+                let message = match &args[0] {
+                    Expr::Property { property } if v => {
+                        format!("comparison is always true unless '{property}' is null")
+                    }
+                    _ => format!("comparison is always {v}"),
+                };
+                warn(pointer.to_string(), message);
+            }
+        }
+        _ => {}
+    }
+    if COMPARISON_OPS.contains(&op.as_ref()) || op == "between" || op == "in" {
+        if let Some(Expr::Property { property }) = args.first() {
+            for (i, arg) in args.iter().enumerate().skip(1) {
+                match arg {
+                    Expr::Array(array) => {
+                        for (j, expr) in array.iter().enumerate() {
+                            if let Some(message) = numeric_string(property, expr) {
+                                warn(format!("{pointer}/args/{i}/{j}"), message);
+                            }
+                        }
+                    }
+                    expr => {
+                        if let Some(message) = numeric_string(property, expr) {
+                            warn(format!("{pointer}/args/{i}"), message);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns a warning message if `expr` is a string literal that looks like a
+/// number.
+fn numeric_string(property: &str, expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Literal(s) if s.trim().parse::<f64>().is_ok() => Some(format!(
+            "'{property}' is compared with the string '{s}', not the number {}",
+            s.trim()
+        )),
+        _ => None,
+    }
+}
+
+/// Returns the result of a comparison, if it doesn't depend on any property.
+fn constant_comparison(op: &str, left: &Expr, right: &Expr) -> Option<bool> {
+    let ordering =
+        if let (Expr::Property { property }, Expr::Property { property: other }) = (left, right) {
+            if property != other {
+                return None;
+            }
+            Ordering::Equal
+        } else if let (Some(left), Some(right)) = (left.as_f64(), right.as_f64()) {
+            left.partial_cmp(&right)?
+        } else if let (Expr::Literal(left), Expr::Literal(right)) = (left, right) {
+            left.cmp(right)
+        } else {
+            return None;
+        };
+    Some(match op {
+        "=" => ordering == Ordering::Equal,
+        "<>" => ordering != Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    })
+}
+
+/// Returns false if the expression is definitely not a string.
+fn could_be_string(expr: &Expr) -> bool {
+    match expr {
+        Expr::Property { .. } | Expr::Literal(_) => true,
+        Expr::Operation { op, .. } => !matches!(
+            op.as_ref(),
+            "and" | "or" | "not" | "like" | "between" | "in" | "isNull" | "bbox"
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expr;
+
+    fn lint(s: &str) -> Vec<String> {
+        let expr: Expr = s.parse().unwrap();
+        expr.lint().iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn numeric_string() {
+        assert_eq!(
+            lint("eo:cloud_cover IN ('10', 20)"),
+            ["at '/args/1/0': 'eo:cloud_cover' is compared with the string '10', not the number 10"]
+        );
+    }
+
+    #[test]
+    fn constant() {
+        assert_eq!(
+            lint("1 = 2 OR platform = platform OR true"),
+            [
+                "at '/args/0': comparison is always false",
+                "at '/args/1': comparison is always true unless 'platform' is null",
+                "at '/args/2': true makes the whole OR always true"
+            ]
+        );
+        assert_eq!(
+            lint("platform < platform"),
+            ["at '': comparison is always false"]
+        );
+        assert!(lint("platform = other").is_empty());
+    }
+
+    #[test]
+    fn casei() {
+        assert_eq!(
+            lint("CASEI(eo:cloud_cover) = CASEI(10)"),
+            ["at '/args/1/args/0': casei is applied to something that isn't a string"]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{expr::is_known_op, Error};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+/// A custom function, e.g. `s_dwithin_geodesic` or `in_catalog`, that
+/// [Expr::to_sql_with_options](crate::Expr::to_sql_with_options) knows how to
+/// write.
+///
+/// cql2-text and cql2-json already read and write any function as
+/// `name(args...)` and `{"op": "name", "args": [...]}`, so plugins only need
+/// to describe how the function becomes SQL.
+///
+/// # Examples
+///
+/// ```
+/// use cql2::{Error, Expr, OperatorPlugin, ToSqlOptions};
+///
+/// #[derive(Debug)]
+/// struct DWithinGeodesic;
+///
+/// impl OperatorPlugin for DWithinGeodesic {
+///     fn name(&self) -> &str {
+///         "s_dwithin_geodesic"
+///     }
+///
+///     fn arity(&self) -> Option<usize> {
+///         Some(3)
+///     }
+///
+///     fn to_sql(&self, args: &[String]) -> Result<String, Error> {
+///         Ok(format!(
+///             "ST_DWithin({}::geography, {}::geography, {})",
+///             args[0], args[1], args[2]
+///         ))
+///     }
+/// }
+///
+/// let options = ToSqlOptions::default().with_operator(DWithinGeodesic).unwrap();
+/// let expr: Expr = "s_dwithin_geodesic(geometry, POINT(0 0), 1000)".parse().unwrap();
+/// let sql = expr.to_sql_with_options(&options).unwrap();
+/// assert_eq!(sql.query, r#"ST_DWithin("geometry"::geography, $1::geography, $2)"#);
+/// ```
+pub trait OperatorPlugin: Debug + Send + Sync {
+    /// The function's name, as written in cql2-text and cql2-json.
+    ///
+    /// Names are case-sensitive, and plugins can't replace built-in
+    /// operators.
+    fn name(&self) -> &str;
+
+    /// The number of arguments the function takes, or `None` if it takes any
+    /// number.
+    fn arity(&self) -> Option<usize> {
+        None
+    }
+
+    /// Writes the function as SQL, given its arguments already written as
+    /// SQL.
+    ///
+    /// Literal arguments are placeholders like `$1`, or SQL literals when
+    /// [inline_params](crate::ToSqlOptions::inline_params) is set.
+    fn to_sql(&self, args: &[String]) -> Result<String, Error>;
+}
+
+/// A registry of [OperatorPlugin]s, keyed by name.
+///
+/// # Examples
+///
+/// ```
+/// use cql2::{Error, OperatorPlugin, Operators};
+///
+/// #[derive(Debug)]
+/// struct InCatalog;
+///
+/// impl OperatorPlugin for InCatalog {
+///     fn name(&self) -> &str {
+///         "in_catalog"
+///     }
+///
+///     fn to_sql(&self, args: &[String]) -> Result<String, Error> {
+///         Ok(format!("(collection = ANY(catalog_collections({})))", args.join(", ")))
+///     }
+/// }
+///
+/// let mut operators = Operators::new();
+/// operators.register(InCatalog).unwrap();
+/// assert!(operators.get("in_catalog").is_some());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Operators {
+    plugins: HashMap<String, Arc<dyn OperatorPlugin>>,
+}
+
+impl Operators {
+    /// Creates an empty registry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Operators;
+    ///
+    /// let operators = Operators::new();
+    /// assert!(operators.is_empty());
+    /// ```
+    pub fn new() -> Operators {
+        Operators::default()
+    }
+
+    /// Registers a plugin, replacing any plugin with the same name.
+    ///
+    /// Returns [Error::BuiltinOperator], and doesn't register the plugin, if
+    /// it's named after a built-in operator, e.g. `=` or `s_intersects`.
+    pub fn register(&mut self, plugin: impl OperatorPlugin + 'static) -> Result<(), Error> {
+        if is_known_op(plugin.name()) {
+            return Err(Error::BuiltinOperator(plugin.name().to_string()));
+        }
+        let _ = self
+            .plugins
+            .insert(plugin.name().to_string(), Arc::new(plugin));
+        Ok(())
+    }
+
+    /// Returns the plugin for a function, if one is registered.
+    pub fn get(&self, name: &str) -> Option<&dyn OperatorPlugin> {
+        self.plugins.get(name).map(|plugin| plugin.as_ref())
+    }
+
+    /// Returns true if no plugins are registered.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Writes a call to a registered function as SQL, or returns `None` if
+    /// no plugin is registered for it.
+    pub(crate) fn to_sql(&self, name: &str, args: &[String]) -> Option<Result<String, Error>> {
+        let plugin = self.get(name)?;
+        Some(match plugin.arity() {
+            Some(expected) if expected != args.len() => Err(Error::InvalidNumberOfArguments {
+                name: name.to_string(),
+                actual: args.len(),
+                expected,
+            }),
+            _ => plugin.to_sql(args),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OperatorPlugin, Operators};
+    use crate::{Error, Expr, ToSqlOptions};
+
+    #[derive(Debug)]
+    struct Plugin(&'static str);
+
+    impl OperatorPlugin for Plugin {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn arity(&self) -> Option<usize> {
+            Some(1)
+        }
+
+        fn to_sql(&self, args: &[String]) -> Result<String, Error> {
+            Ok(format!("plugin({})", args[0]))
+        }
+    }
+
+    #[test]
+    fn builtins_are_not_replaced() {
+        let mut operators = Operators::new();
+        assert!(matches!(
+            operators.register(Plugin("s_intersects")),
+            Err(Error::BuiltinOperator(name)) if name == "s_intersects"
+        ));
+        assert!(operators.is_empty());
+        assert!(ToSqlOptions::default().with_operator(Plugin("=")).is_err());
+    }
+
+    #[test]
+    fn arity() {
+        let options = ToSqlOptions::default()
+            .with_operator(Plugin("inCatalog"))
+            .unwrap();
+        let expr: Expr = "inCatalog(collection)".parse().unwrap();
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            r#"plugin("collection")"#
+        );
+        let expr: Expr = "inCatalog(collection, 'landsat')".parse().unwrap();
+        assert!(matches!(
+            expr.to_sql_with_options(&options),
+            Err(Error::InvalidNumberOfArguments {
+                actual: 2,
+                expected: 1,
+                ..
+            })
+        ));
+        let expr: Expr = "incatalog(collection)".parse().unwrap();
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            r#"incatalog("collection")"#
+        );
+    }
+}
@@ -1,9 +1,13 @@
-use crate::{Error, Expr, Geometry};
+use crate::{
+    expr::{intern_op, is_known_op},
+    Error, Expr, Geometry, SourceMap, DEFAULT_MAX_DEPTH,
+};
 use pest::{
     iterators::{Pair, Pairs},
     pratt_parser::PrattParser,
     Parser,
 };
+use std::ops::Range;
 
 /// Parses a cql2-text string into a CQL2 expression.
 ///
@@ -14,18 +18,152 @@ use pest::{
 /// let expr = cql2::parse_text(s);
 /// ```
 pub fn parse_text(s: &str) -> Result<Expr, Error> {
+    parse_text_with_max_depth(s, DEFAULT_MAX_DEPTH)
+}
+
+/// Parses a cql2-text string into a CQL2 expression, rejecting expressions
+/// nested more than `max_depth` levels deep.
+///
+/// [parse_text] uses [DEFAULT_MAX_DEPTH](crate::DEFAULT_MAX_DEPTH).
+///
+/// # Examples
+///
+/// ```
+/// let s = "NOT (a = 1 OR b = 2)";
+/// assert!(cql2::parse_text_with_max_depth(s, 4).is_ok());
+/// assert!(cql2::parse_text_with_max_depth(s, 3).is_err());
+/// ```
+pub fn parse_text_with_max_depth(s: &str, max_depth: usize) -> Result<Expr, Error> {
+    check_nesting(s, max_depth)?;
+    let mut pairs = CQL2Parser::parse(Rule::Expr, s).map_err(Box::new)?;
+    if let Some(pair) = pairs.next() {
+        if pairs.next().is_some() {
+            Err(Error::InvalidCql2Text(s.to_string()))
+        } else {
+            let expr = parse_expr(pair.into_inner())?;
+            if let Err(err) = expr.check_depth(max_depth) {
+                expr.drop_iteratively();
+                return Err(err);
+            }
+            Ok(expr)
+        }
+    } else {
+        Err(Error::InvalidCql2Text(s.to_string()))
+    }
+}
+
+/// Parses a cql2-text string into a CQL2 expression and a [SourceMap] that
+/// locates the parts of the expression in the text.
+///
+/// # Examples
+///
+/// ```
+/// let s = "landsat:scene_id = 'LC82030282019133LGN00'";
+/// let (expr, source_map) = cql2::parse_text_with_source_map(s).unwrap();
+/// assert_eq!(source_map.span("/args/0"), Some(0..16));
+/// ```
+pub fn parse_text_with_source_map(s: &str) -> Result<(Expr, SourceMap), Error> {
+    check_nesting(s, DEFAULT_MAX_DEPTH)?;
     let mut pairs = CQL2Parser::parse(Rule::Expr, s).map_err(Box::new)?;
     if let Some(pair) = pairs.next() {
         if pairs.next().is_some() {
             Err(Error::InvalidCql2Text(s.to_string()))
         } else {
-            parse_expr(pair.into_inner())
+            let mut atoms = Vec::new();
+            collect_atoms(pair.clone(), &mut atoms);
+            let expr = parse_expr(pair.into_inner())?;
+            if let Err(err) = expr.check_depth(DEFAULT_MAX_DEPTH) {
+                expr.drop_iteratively();
+                return Err(err);
+            }
+            let source_map = SourceMap::new(&expr, atoms);
+            Ok((expr, source_map))
         }
     } else {
         Err(Error::InvalidCql2Text(s.to_string()))
     }
 }
 
+/// Returns an error if the text might nest more than `max_depth` deep, before
+/// the recursive parser sees it.
+///
+/// This counts parentheses and runs of prefix `NOT`s and negative signs, so
+/// it over-estimates the depth of the parsed expression.
+fn check_nesting(s: &str, max_depth: usize) -> Result<(), Error> {
+    let mut parentheses = 0usize;
+    let mut prefixes = 0usize;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                for next in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+                prefixes = 0;
+            }
+            '(' => parentheses += 1,
+            ')' => {
+                parentheses = parentheses.saturating_sub(1);
+                prefixes = 0;
+            }
+            '-' => prefixes += 1,
+            c if c.is_alphabetic() => {
+                let mut word = String::from(c);
+                while let Some(next) = chars.next_if(|c| c.is_alphanumeric()) {
+                    word.push(next);
+                }
+                if word.eq_ignore_ascii_case("not") {
+                    prefixes += 1;
+                } else {
+                    prefixes = 0;
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => prefixes = 0,
+        }
+        if parentheses + prefixes > max_depth {
+            return Err(Error::MaxDepthExceeded(max_depth));
+        }
+    }
+    Ok(())
+}
+
+/// Collects the spans of the atoms that become leaves of the parsed
+/// expression, in order.
+///
+/// A negative sign is an atom because it is parsed as multiplication by -1.
+fn collect_atoms(pair: Pair<'_, Rule>, atoms: &mut Vec<Range<usize>>) {
+    match pair.as_rule() {
+        Rule::Unsigned
+        | Rule::DECIMAL
+        | Rule::Double
+        | Rule::Integer
+        | Rule::SingleQuotedString
+        | Rule::True
+        | Rule::False
+        | Rule::Null
+        | Rule::Identifier
+        | Rule::GEOMETRY
+        | Rule::Negative => {
+            let span = pair.as_span();
+            atoms.push(span.start()..span.end());
+        }
+        Rule::Function => {
+            // The first inner pair is the function name.
+            for pair in pair.into_inner().skip(1) {
+                collect_atoms(pair, atoms);
+            }
+        }
+        _ => {
+            for pair in pair.into_inner() {
+                collect_atoms(pair, atoms);
+            }
+        }
+    }
+}
+
 #[derive(pest_derive::Parser)]
 #[grammar = "cql2.pest"]
 struct CQL2Parser;
@@ -82,6 +220,15 @@ fn strip_quotes(s: &str) -> &str {
     }
 }
 
+/// Removes the double quotes around an identifier, if any, and unescapes
+/// doubled quotes inside it.
+fn unquote_identifier(s: &str) -> String {
+    match s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(s) => s.replace("\"\"", "\""),
+        None => s.to_string(),
+    }
+}
+
 fn opstr(op: Pair<'_, Rule>) -> String {
     normalize_op(op.as_str())
 }
@@ -90,8 +237,9 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
     PRATT_PARSER
         .map_primary(|primary| match primary.as_rule() {
             Rule::Expr | Rule::ExpressionInParentheses => parse_expr(primary.into_inner()),
-            Rule::Unsigned => Ok(Expr::Float(primary.as_str().parse::<f64>()?)),
-            Rule::DECIMAL => Ok(Expr::Float(primary.as_str().parse::<f64>()?)),
+            Rule::Unsigned | Rule::DECIMAL | Rule::Double | Rule::Integer => {
+                Ok(Expr::Float(primary.as_str().parse::<f64>()?))
+            }
             Rule::SingleQuotedString => {
                 Ok(Expr::Literal(strip_quotes(primary.as_str()).to_string()))
             }
@@ -99,8 +247,9 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                 let bool_value = primary.as_str().to_lowercase().parse::<bool>()?;
                 Ok(Expr::Bool(bool_value))
             }
+            Rule::Null => Ok(Expr::Null),
             Rule::Identifier => Ok(Expr::Property {
-                property: strip_quotes(primary.as_str()).to_string(),
+                property: unquote_identifier(primary.as_str()),
             }),
             Rule::GEOMETRY => {
                 // These are some incredibly annoying backflips to handle
@@ -128,16 +277,16 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
             }
             Rule::Function => {
                 let mut pairs = primary.into_inner();
-                let op = strip_quotes(
+                let name = unquote_identifier(
                     pairs
                         .next()
                         .expect("the grammar guarantees that there is always an op")
                         .as_str(),
-                )
-                .to_lowercase();
+                );
+                let op = name.to_lowercase();
                 let mut args = Vec::new();
                 for pair in pairs {
-                    args.push(Box::new(parse_expr(pair.into_inner())?))
+                    args.push(parse_expr(pair.into_inner())?)
                 }
                 match op.as_str() {
                     "interval" => Ok(Expr::Interval { interval: args }),
@@ -145,22 +294,40 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                         date: args
                             .into_iter()
                             .next()
+                            .map(Box::new)
                             .ok_or(Error::MissingArgument("date"))?,
                     }),
+                    "duration" => Ok(Expr::Duration {
+                        duration: args
+                            .into_iter()
+                            .next()
+                            .map(Box::new)
+                            .ok_or(Error::MissingArgument("duration"))?,
+                    }),
                     "timestamp" => Ok(Expr::Timestamp {
                         timestamp: args
                             .into_iter()
                             .next()
+                            .map(Box::new)
                             .ok_or(Error::MissingArgument("timestamp"))?,
                     }),
-                    _ => Ok(Expr::Operation { op, args }),
+                    // Built-in functions are case-insensitive, but other
+                    // functions keep their casing for case-sensitive backends.
+                    _ if is_known_op(&op) => Ok(Expr::Operation {
+                        op: intern_op(op),
+                        args,
+                    }),
+                    _ => Ok(Expr::Operation {
+                        op: intern_op(name),
+                        args,
+                    }),
                 }
             }
             Rule::Array => {
                 let pairs = primary.into_inner();
                 let mut array_elements = Vec::new();
                 for pair in pairs {
-                    array_elements.push(Box::new(parse_expr(pair.into_inner())?))
+                    array_elements.push(parse_expr(pair.into_inner())?)
                 }
                 Ok(Expr::Array(array_elements))
             }
@@ -178,20 +345,18 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                 notflag = true;
             }
 
-            let origargs = vec![Box::new(lhs.clone()), Box::new(rhs.clone())];
             let mut retexpr: Expr;
-            let mut lhsclone = lhs.clone();
-            let rhsclone = rhs.clone();
-
-            let mut lhsargs: Vec<Box<Expr>> = Vec::new();
-            let mut rhsargs: Vec<Box<Expr>> = Vec::new();
-            let mut betweenargs: Vec<Box<Expr>> = Vec::new();
 
             if opstring == "between" {
+                let mut lhsclone = lhs;
+                let mut lhsargs: Vec<Expr> = Vec::new();
+                let mut rhsargs: Vec<Expr> = Vec::new();
+                let mut betweenargs: Vec<Expr> = Vec::new();
+
                 match &lhsclone {
                     Expr::Operation { op, args } if op == "and" => {
                         lhsargs = args.to_vec();
-                        lhsclone = *lhsargs.pop().unwrap();
+                        lhsclone = lhsargs.pop().unwrap();
                     }
                     _ => (),
                 }
@@ -199,13 +364,13 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                 match &lhsclone {
                     Expr::Operation { op, args } if op == "not" => {
                         lhsargs = args.to_vec();
-                        lhsclone = *lhsargs.pop().unwrap();
+                        lhsclone = lhsargs.pop().unwrap();
                         notflag = true;
                     }
                     _ => (),
                 }
                 let betweenleft = lhsclone.to_owned();
-                betweenargs.push(Box::new(betweenleft));
+                betweenargs.push(betweenleft);
 
                 match &rhs {
                     Expr::Operation { op, args } if op == "and" => {
@@ -218,14 +383,14 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                 }
 
                 retexpr = Expr::Operation {
-                    op: "between".to_string(),
+                    op: "between".into(),
                     args: betweenargs,
                 };
 
                 if notflag {
                     retexpr = Expr::Operation {
-                        op: "not".to_string(),
-                        args: vec![Box::new(retexpr)],
+                        op: "not".into(),
+                        args: vec![retexpr],
                     };
                 };
 
@@ -233,14 +398,14 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                     return Ok(retexpr);
                 }
 
-                let mut andargs: Vec<Box<Expr>> = Vec::new();
+                let mut andargs: Vec<Expr> = Vec::new();
 
                 if !lhsargs.is_empty() {
                     for a in lhsargs.into_iter() {
                         andargs.push(a);
                     }
                 }
-                andargs.push(Box::new(retexpr));
+                andargs.push(retexpr);
 
                 if !rhsargs.is_empty() {
                     for a in rhsargs.into_iter() {
@@ -249,35 +414,30 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
                 }
 
                 return Ok(Expr::Operation {
-                    op: "and".to_string(),
+                    op: "and".into(),
                     args: andargs,
                 });
             } else {
-                let mut outargs: Vec<Box<Expr>> = Vec::new();
-
-                match lhsclone {
-                    Expr::Operation { ref op, ref args } if op == "and" && op == &opstring => {
-                        for arg in args.iter() {
-                            outargs.push(arg.clone());
-                        }
-                        outargs.push(Box::new(rhsclone));
-                        return Ok(Expr::Operation {
-                            op: opstring,
-                            args: outargs,
-                        });
+                // Moves rather than clones, since chains of ANDs and ORs make
+                // the left-hand side arbitrarily large.
+                retexpr = match lhs {
+                    Expr::Operation { op, mut args }
+                        if (op == "and" || op == "or") && op == opstring =>
+                    {
+                        args.push(rhs);
+                        return Ok(Expr::Operation { op, args });
                     }
-                    _ => (),
-                }
-                retexpr = Expr::Operation {
-                    op: opstring,
-                    args: origargs,
+                    lhs => Expr::Operation {
+                        op: intern_op(opstring),
+                        args: vec![lhs, rhs],
+                    },
                 };
             }
 
             if notflag {
                 return Ok(Expr::Operation {
-                    op: "not".to_string(),
-                    args: vec![Box::new(retexpr)],
+                    op: "not".into(),
+                    args: vec![retexpr],
                 });
             }
             Ok(retexpr)
@@ -286,12 +446,12 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
             let child = child?;
             match op.as_rule() {
                 Rule::UnaryNot => Ok(Expr::Operation {
-                    op: "not".to_string(),
-                    args: vec![Box::new(child)],
+                    op: "not".into(),
+                    args: vec![child],
                 }),
                 Rule::Negative => Ok(Expr::Operation {
-                    op: "*".to_string(),
-                    args: vec![Box::new(Expr::Float(-1.0)), Box::new(child)],
+                    op: "*".into(),
+                    args: vec![Expr::Float(-1.0), child],
                 }),
                 rule => unreachable!("Expr::parse expected prefix operator, found {:?}", rule),
             }
@@ -301,15 +461,15 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
             let notflag = &op.clone().into_inner().next().is_some();
             let retexpr = match op.as_rule() {
                 Rule::IsNullPostfix => Expr::Operation {
-                    op: "isNull".to_string(),
-                    args: vec![Box::new(child)],
+                    op: "isNull".into(),
+                    args: vec![child],
                 },
                 rule => unreachable!("Expr::parse expected postfix operator, found {:?}", rule),
             };
             if *notflag {
                 return Ok(Expr::Operation {
-                    op: "not".to_string(),
-                    args: vec![Box::new(retexpr)],
+                    op: "not".into(),
+                    args: vec![retexpr],
                 });
             };
             Ok(retexpr)
@@ -320,10 +480,73 @@ fn parse_expr(expression_pairs: Pairs<'_, Rule>) -> Result<Expr, Error> {
 #[cfg(test)]
 mod tests {
     use super::{CQL2Parser, Rule};
+    use crate::{Error, Expr};
     use pest::Parser;
 
     #[test]
     fn point_zm() {
         let _ = CQL2Parser::parse(Rule::GEOMETRY, "POINT ZM(-105.1019 40.1672 4981 42)").unwrap();
     }
+
+    #[test]
+    fn identifiers() {
+        for (property, text, sql) in [
+            (
+                "properties.eo:cloud_cover",
+                r#""properties.eo:cloud_cover""#,
+                r#""properties.eo:cloud_cover""#,
+            ),
+            ("höhe", r#""höhe""#, r#""höhe""#),
+            ("a b", r#""a b""#, r#""a b""#),
+            (r#"a"b"#, r#""a""b""#, r#""a""b""#),
+            ("and", r#""and""#, r#""and""#),
+        ] {
+            let expr = Expr::Property {
+                property: property.to_string(),
+            };
+            assert_eq!(expr.to_text().unwrap(), text);
+            assert_eq!(expr.to_sql().unwrap().query, sql);
+            assert_eq!(text.parse::<Expr>().unwrap(), expr);
+        }
+        let expr: Expr = "properties.eo:cloud_cover < 10 AND höhe > 2"
+            .parse()
+            .unwrap();
+        assert_eq!(expr.properties(), ["properties.eo:cloud_cover", "höhe"]);
+    }
+
+    #[test]
+    fn function_casing() {
+        let expr: Expr = "MyFunc(a) = 1 AND S_INTERSECTS(geometry, POINT(0 0))"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.to_text().unwrap(),
+            r#"(("MyFunc"(a) = 1) AND s_intersects(geometry, POINT(0 0)))"#
+        );
+        assert!(expr.to_json().unwrap().contains(r#""op":"MyFunc""#));
+        assert_eq!(
+            expr.to_sql().unwrap().query,
+            r#"(("MyFunc"("a") = $1) AND s_intersects("geometry", $2))"#
+        );
+        let roundtrip: Expr = expr.to_text().unwrap().parse().unwrap();
+        assert_eq!(roundtrip, expr);
+    }
+
+    #[test]
+    fn too_deep() {
+        for s in [
+            "NOT ".repeat(10_000) + "a = 1",
+            "(".repeat(10_000) + "a = 1" + &")".repeat(10_000),
+        ] {
+            assert!(matches!(
+                crate::parse_text(&s).unwrap_err(),
+                Error::MaxDepthExceeded(crate::DEFAULT_MAX_DEPTH)
+            ));
+        }
+        for op in [" AND ", " OR "] {
+            let s = vec!["a = 1"; 10_000].join(op);
+            let expr = crate::parse_text(&s).unwrap();
+            assert_eq!(crate::parse_text(&expr.to_text().unwrap()).unwrap(), expr);
+        }
+    }
 }
@@ -0,0 +1,320 @@
+use crate::{extent::DATETIME_PROPERTIES, Error, Expr};
+use jiff::Timestamp;
+use std::fmt::{Display, Formatter};
+
+/// The GeoParquet bbox covering columns, in xmin, ymin, xmax, ymax order.
+const BBOX_COLUMNS: [&str; 4] = ["bbox.xmin", "bbox.ymin", "bbox.xmax", "bbox.ymax"];
+
+/// Column predicates and a residual expression derived from a CQL2 filter.
+///
+/// The predicates can be checked against column statistics (e.g. Parquet
+/// row group min/max values) to skip data that can't match. Rows that pass
+/// the predicates must still be checked against the residual expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pruning {
+    /// Predicates on single columns, all of which must hold for a row to match.
+    pub predicates: Vec<ColumnPredicate>,
+
+    /// The part of the filter that isn't exactly captured by the predicates.
+    ///
+    /// This is `true` if the predicates capture the whole filter.
+    pub residual: Expr,
+}
+
+/// A comparison between a column and a constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnPredicate {
+    /// The column name.
+    pub column: String,
+
+    /// The comparison operator.
+    pub op: ComparisonOp,
+
+    /// The value to compare against.
+    pub value: Scalar,
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOp {
+    /// `=`
+    Eq,
+
+    /// `<>`
+    NotEq,
+
+    /// `<`
+    Lt,
+
+    /// `<=`
+    LtEq,
+
+    /// `>`
+    Gt,
+
+    /// `>=`
+    GtEq,
+}
+
+/// A constant value in a [ColumnPredicate].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    /// A number.
+    Number(f64),
+
+    /// A string.
+    String(String),
+
+    /// A boolean.
+    Bool(bool),
+
+    /// A timestamp.
+    Timestamp(Timestamp),
+}
+
+impl Expr {
+    /// Splits this expression into column predicates, for pruning with column
+    /// statistics, and a residual expression.
+    ///
+    /// The top-level AND arguments of the expression are converted
+    /// independently:
+    ///
+    /// - Comparisons between a property and a number, string, boolean, or
+    ///   timestamp become a predicate and are removed from the residual.
+    /// - Operations with a [spatial extent](Expr::spatial_extent) add predicates
+    ///   on the GeoParquet bbox covering columns (`bbox.xmin`, `bbox.ymin`,
+    ///   `bbox.xmax`, and `bbox.ymax`). Bounding boxes that cross the
+    ///   antimeridian don't have a spatial extent, so they add none.
+    /// - Operations with a [temporal extent](Expr::temporal_extent) add
+    ///   predicates on the `datetime`, `start_datetime`, or `end_datetime`
+    ///   column that they use. Operations that use more than one of them,
+    ///   e.g. `INTERVAL(start_datetime, end_datetime)`, add none.
+    ///
+    /// Spatial and temporal predicates are coarser than the operations they
+    /// come from, so those operations stay in the residual.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{ComparisonOp, Expr, Scalar};
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 10 AND s_intersects(geometry, BBOX(5, 40, 6, 41))"
+    ///     .parse()
+    ///     .unwrap();
+    /// let pruning = expr.pruning().unwrap();
+    /// assert_eq!(pruning.predicates.len(), 5);
+    /// assert_eq!(pruning.predicates[0].column, "eo:cloud_cover");
+    /// assert_eq!(pruning.predicates[0].op, ComparisonOp::Lt);
+    /// assert_eq!(pruning.predicates[0].value, Scalar::Number(10.));
+    /// assert_eq!(
+    ///     pruning.residual.to_text().unwrap(),
+    ///     "s_intersects(geometry, bbox(5, 40, 6, 41))"
+    /// );
+    /// ```
+    pub fn pruning(&self) -> Result<Pruning, Error> {
+        let conjuncts = match self {
+            Expr::Operation { op, args } if op == "and" => args.iter().collect(),
+            expr => vec![expr],
+        };
+        let mut predicates = Vec::new();
+        let mut residual = Vec::new();
+        for conjunct in conjuncts {
+            if let Some(predicate) = column_predicate(conjunct) {
+                predicates.push(predicate);
+                continue;
+            }
+            if let Some(rect) = conjunct.spatial_extent()?.bounded() {
+                let bounds = [
+                    (BBOX_COLUMNS[0], ComparisonOp::LtEq, rect.max().x),
+                    (BBOX_COLUMNS[1], ComparisonOp::LtEq, rect.max().y),
+                    (BBOX_COLUMNS[2], ComparisonOp::GtEq, rect.min().x),
+                    (BBOX_COLUMNS[3], ComparisonOp::GtEq, rect.min().y),
+                ];
+                predicates.extend(bounds.map(|(column, op, value)| ColumnPredicate {
+                    column: column.to_string(),
+                    op,
+                    value: Scalar::Number(value),
+                }));
+            }
+            let datetime_properties: Vec<&str> = conjunct
+                .properties()
+                .into_iter()
+                .filter(|property| DATETIME_PROPERTIES.contains(property))
+                .collect();
+            if let [column] = datetime_properties.as_slice() {
+                if let Some(date_range) = conjunct.temporal_extent()?.bounded() {
+                    if date_range.start != Timestamp::MIN {
+                        predicates.push(ColumnPredicate {
+                            column: column.to_string(),
+                            op: ComparisonOp::GtEq,
+                            value: Scalar::Timestamp(date_range.start),
+                        });
+                    }
+                    if date_range.end != Timestamp::MAX {
+                        predicates.push(ColumnPredicate {
+                            column: column.to_string(),
+                            op: ComparisonOp::LtEq,
+                            value: Scalar::Timestamp(date_range.end),
+                        });
+                    }
+                }
+            }
+            residual.push(conjunct.clone());
+        }
+        Ok(Pruning {
+            predicates,
+            residual: Expr::and_all(residual),
+        })
+    }
+}
+
+fn column_predicate(expr: &Expr) -> Option<ColumnPredicate> {
+    let Expr::Operation { op, args } = expr else {
+        return None;
+    };
+    let op = ComparisonOp::from_op(op)?;
+    if args.len() != 2 {
+        return None;
+    }
+    let (column, op, value) = match (&args[0], &args[1]) {
+        (Expr::Property { property }, value) => (property, op, value),
+        (value, Expr::Property { property }) => (property, op.mirror(), value),
+        _ => return None,
+    };
+    Some(ColumnPredicate {
+        column: column.clone(),
+        op,
+        value: Scalar::from_expr(value)?,
+    })
+}
+
+impl ComparisonOp {
+    fn from_op(op: &str) -> Option<ComparisonOp> {
+        match op {
+            "=" => Some(ComparisonOp::Eq),
+            "<>" => Some(ComparisonOp::NotEq),
+            "<" => Some(ComparisonOp::Lt),
+            "<=" => Some(ComparisonOp::LtEq),
+            ">" => Some(ComparisonOp::Gt),
+            ">=" => Some(ComparisonOp::GtEq),
+            _ => None,
+        }
+    }
+
+    /// Returns the operator with its operands swapped, e.g. `a < b` is `b > a`.
+    fn mirror(self) -> ComparisonOp {
+        match self {
+            ComparisonOp::Lt => ComparisonOp::Gt,
+            ComparisonOp::LtEq => ComparisonOp::GtEq,
+            ComparisonOp::Gt => ComparisonOp::Lt,
+            ComparisonOp::GtEq => ComparisonOp::LtEq,
+            op => op,
+        }
+    }
+}
+
+impl Scalar {
+    fn from_expr(expr: &Expr) -> Option<Scalar> {
+        match expr {
+            Expr::Literal(s) => Some(Scalar::String(s.clone())),
+            Expr::Bool(v) => Some(Scalar::Bool(*v)),
+            Expr::Timestamp { timestamp } => match timestamp.as_ref() {
+                Expr::Literal(s) => s.parse().ok().map(Scalar::Timestamp),
+                _ => None,
+            },
+            expr => expr.as_f64().map(Scalar::Number),
+        }
+    }
+}
+
+impl Display for ComparisonOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ComparisonOp::Eq => "=",
+            ComparisonOp::NotEq => "<>",
+            ComparisonOp::Lt => "<",
+            ComparisonOp::LtEq => "<=",
+            ComparisonOp::Gt => ">",
+            ComparisonOp::GtEq => ">=",
+        };
+        f.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComparisonOp, Scalar};
+    use crate::Expr;
+
+    #[test]
+    fn mirrored_comparison() {
+        let expr: Expr = "10 > eo:cloud_cover".parse().unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert_eq!(pruning.predicates.len(), 1);
+        assert_eq!(pruning.predicates[0].op, ComparisonOp::Lt);
+        assert_eq!(pruning.residual, Expr::Bool(true));
+    }
+
+    #[test]
+    fn temporal() {
+        let expr: Expr =
+            "t_intersects(datetime, INTERVAL('2020-01-01T00:00:00Z', '2020-02-01T00:00:00Z')) AND datetime < TIMESTAMP('2020-01-15T00:00:00Z')"
+                .parse()
+                .unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert_eq!(pruning.predicates.len(), 3);
+        assert_eq!(
+            pruning.predicates[0].value,
+            Scalar::Timestamp("2020-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(pruning.predicates[2].op, ComparisonOp::Lt);
+        assert_eq!(
+            pruning.residual.to_text().unwrap(),
+            "t_intersects(datetime, INTERVAL('2020-01-01T00:00:00Z','2020-02-01T00:00:00Z'))"
+        );
+    }
+
+    #[test]
+    fn temporal_property() {
+        let expr: Expr = "t_after(start_datetime, TIMESTAMP('2020-01-01T00:00:00Z'))"
+            .parse()
+            .unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert_eq!(pruning.predicates.len(), 1);
+        assert_eq!(pruning.predicates[0].column, "start_datetime");
+        assert_eq!(pruning.predicates[0].op, ComparisonOp::GtEq);
+
+        let expr: Expr = "t_during(INTERVAL(start_datetime, end_datetime), \
+            INTERVAL('2020-01-01', '2020-01-31'))"
+            .parse()
+            .unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert!(pruning.predicates.is_empty());
+        assert_eq!(pruning.residual, expr);
+    }
+
+    #[test]
+    fn antimeridian() {
+        let expr: Expr = "s_intersects(geometry, BBOX(170, -10, -170, 10))"
+            .parse()
+            .unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert!(pruning.predicates.is_empty());
+        assert_eq!(pruning.residual, expr);
+    }
+
+    #[test]
+    fn not_prunable() {
+        let expr: Expr = "foo LIKE 'bar%' OR baz = 1".parse().unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert!(pruning.predicates.is_empty());
+        assert_eq!(pruning.residual, expr);
+    }
+
+    #[test]
+    fn negative_number() {
+        let expr: Expr = "elevation > -10".parse().unwrap();
+        let pruning = expr.pruning().unwrap();
+        assert_eq!(pruning.predicates[0].value, Scalar::Number(-10.));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{expr::quote_sql_identifier, Error, Expr};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+/// An OGC API queryables document.
+///
+/// Queryables are a JSON Schema describing the properties that can be used in
+/// a filter. Only the `properties` of the schema are used.
+///
+/// # Examples
+///
+/// ```
+/// use cql2::{Queryables, Type};
+///
+/// let queryables: Queryables = r#"{
+///     "type": "object",
+///     "properties": {
+///         "eo:cloud_cover": { "type": "number" },
+///         "datetime": { "type": "string", "format": "date-time" }
+///     }
+/// }"#.parse().unwrap();
+/// assert_eq!(queryables.get_type("eo:cloud_cover"), Some(Type::Number));
+/// assert_eq!(queryables.get_type("datetime"), Some(Type::Temporal));
+/// assert_eq!(queryables.get_type("foo"), None);
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Queryables {
+    /// The queryable properties, as JSON Schemas.
+    #[serde(default)]
+    pub properties: Map<String, Value>,
+}
+
+/// The type of a queryable property or of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    /// A boolean.
+    Boolean,
+
+    /// A number.
+    Number,
+
+    /// A string.
+    String,
+
+    /// A date, timestamp, or interval.
+    Temporal,
+
+    /// A geometry or bounding box.
+    Geometry,
+
+    /// An array.
+    Array,
+
+    /// An object.
+    Object,
+
+    /// The null value.
+    Null,
+}
+
+impl Queryables {
+    /// Returns the JSON Schema for a queryable property.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Queryables;
+    ///
+    /// let queryables = Queryables::default();
+    /// assert!(queryables.get("foo").is_none());
+    /// ```
+    pub fn get(&self, property: &str) -> Option<&Value> {
+        self.properties.get(property)
+    }
+
+    /// Returns the type of a queryable property.
+    ///
+    /// Returns `None` if the property isn't a queryable or its schema doesn't
+    /// declare a recognizable type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Queryables, Type};
+    /// use serde_json::json;
+    ///
+    /// let mut queryables = Queryables::default();
+    /// queryables.properties.insert(
+    ///     "geometry".to_string(),
+    ///     json!({"$ref": "https://geojson.org/schema/Geometry.json"}),
+    /// );
+    /// assert_eq!(queryables.get_type("geometry"), Some(Type::Geometry));
+    /// ```
+    pub fn get_type(&self, property: &str) -> Option<Type> {
+        self.get(property).and_then(schema_type)
+    }
+
+    /// Returns the properties used in an expression that aren't queryables.
+    ///
+    /// Each property is returned once, in the order it first appears.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Queryables};
+    ///
+    /// let queryables: Queryables = r#"{"properties": {"eo:cloud_cover": {"type": "number"}}}"#
+    ///     .parse()
+    ///     .unwrap();
+    /// let expr: Expr = "eo:cloud_cover < 10 AND platform = 'landsat-8'".parse().unwrap();
+    /// assert_eq!(queryables.unknown_properties(&expr), ["platform"]);
+    /// ```
+    pub fn unknown_properties(&self, expr: &Expr) -> Vec<String> {
+        expr.properties()
+            .into_iter()
+            .filter(|property| !self.properties.contains_key(*property))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Returns the SQL expression for a queryable property, or `None` if the
+    /// property isn't a queryable.
+    ///
+    /// If the property's schema has an `x-column` string, it is used as-is,
+    /// so it must be trusted. Because these are usually JSON accessors that
+    /// return text, `x-column` expressions are cast according to the schema:
+    /// numbers to `numeric`, `date-time` strings to `timestamptz`, `date`
+    /// strings to `date`, and booleans to `boolean`. Other properties are
+    /// written as quoted identifiers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Queryables;
+    ///
+    /// let queryables: Queryables = r#"{"properties": {
+    ///     "datetime": {"type": "string", "format": "date-time", "x-column": "properties->>'datetime'"},
+    ///     "id": {"type": "string"}
+    /// }}"#.parse().unwrap();
+    /// assert_eq!(
+    ///     queryables.sql_column("datetime").unwrap(),
+    ///     "(properties->>'datetime')::timestamptz"
+    /// );
+    /// assert_eq!(queryables.sql_column("id").unwrap(), "\"id\"");
+    /// assert!(queryables.sql_column("foo").is_none());
+    /// ```
+    pub fn sql_column(&self, property: &str) -> Option<String> {
+        let schema = self.get(property)?;
+        let Some(column) = schema.get("x-column").and_then(Value::as_str) else {
+            return Some(quote_sql_identifier(property));
+        };
+        let format = schema.get("format").and_then(Value::as_str);
+        let cast = match schema_type(schema) {
+            Some(Type::Number) => Some("numeric"),
+            Some(Type::Boolean) => Some("boolean"),
+            Some(Type::Temporal) if format == Some("date") => Some("date"),
+            Some(Type::Temporal) => Some("timestamptz"),
+            _ => None,
+        };
+        Some(match cast {
+            Some(cast) => format!("({column})::{cast}"),
+            None => column.to_string(),
+        })
+    }
+}
+
+impl FromStr for Queryables {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Queryables, Error> {
+        serde_json::from_str(s).map_err(Error::from)
+    }
+}
+
+fn schema_type(schema: &Value) -> Option<Type> {
+    let schema = schema.as_object()?;
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if reference.contains("geojson.org") {
+            return Some(Type::Geometry);
+        }
+    }
+    let format = schema.get("format").and_then(Value::as_str);
+    if format.is_some_and(|format| format.starts_with("geometry")) {
+        return Some(Type::Geometry);
+    }
+    let type_name = match schema.get("type")? {
+        Value::String(s) => s.as_str(),
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|s| *s != "null")?,
+        _ => return None,
+    };
+    match type_name {
+        "string" => match format {
+            Some("date-time") | Some("date") => Some(Type::Temporal),
+            _ => Some(Type::String),
+        },
+        "number" | "integer" => Some(Type::Number),
+        "boolean" => Some(Type::Boolean),
+        "array" => Some(Type::Array),
+        "object" => Some(Type::Object),
+        "null" => Some(Type::Null),
+        _ => None,
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Type::Boolean => "boolean",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Temporal => "temporal",
+            Type::Geometry => "geometry",
+            Type::Array => "array",
+            Type::Object => "object",
+            Type::Null => "null",
+        };
+        f.write_str(s)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{Expr, Geometry};
+
+/// The placeholder for string literals.
+const STRING: &str = "?";
+
+/// The placeholder for dates, and the date of timestamp and interval
+/// placeholders.
+const DATE: &str = "1970-01-01";
+
+impl Expr {
+    /// Returns a copy of this expression with its literals replaced by
+    /// placeholders, so that it can be logged without leaking user data.
+    ///
+    /// Operators, properties, and the number of arguments are kept, and the
+    /// result is still valid CQL2 if the input was, so redacted expressions
+    /// can be grouped for analytics. The placeholders are:
+    ///
+    /// - `'?'` for strings
+    /// - `0` for numbers, including bounding box coordinates
+    /// - `POINT(0 0)` for geometries
+    /// - `DATE('1970-01-01')`, `TIMESTAMP('1970-01-01T00:00:00Z')`, and
+    ///   `INTERVAL('1970-01-01','1970-01-01')` for temporal values, although
+    ///   open interval bounds stay open
+    /// - `'P0D'` for durations
+    ///
+    /// Booleans and `NULL` are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "id = 'LC08' AND eo:cloud_cover < 10 \
+    ///     AND s_intersects(geometry, POLYGON((0 0, 1 0, 1 1, 0 0)))"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     expr.redact_literals().to_text().unwrap(),
+    ///     "((id = '?') AND (\"eo:cloud_cover\" < 0) AND s_intersects(geometry, POINT(0 0)))"
+    /// );
+    /// ```
+    pub fn redact_literals(&self) -> Expr {
+        match self {
+            // cql2-text parses negative numbers as multiplication by `-1`.
+            Expr::Operation { op, args }
+                if op == "*"
+                    && args.len() == 2
+                    && args[0] == Expr::Float(-1.0)
+                    && matches!(args[1], Expr::Float(_)) =>
+            {
+                Expr::Float(0.0)
+            }
+            Expr::Operation { op, args } => Expr::Operation {
+                op: op.clone(),
+                args: args.iter().map(Expr::redact_literals).collect(),
+            },
+            Expr::Interval { interval } => Expr::Interval {
+                interval: interval
+                    .iter()
+                    .map(|bound| match bound {
+                        Expr::Literal(v) if v == ".." => bound.clone(),
+                        Expr::Literal(_) => Expr::Literal(DATE.to_string()),
+                        bound => bound.redact_literals(),
+                    })
+                    .collect(),
+            },
+            Expr::Timestamp { .. } => Expr::Timestamp {
+                timestamp: Box::new(Expr::Literal(format!("{DATE}T00:00:00Z"))),
+            },
+            Expr::Date { .. } => Expr::Date {
+                date: Box::new(Expr::Literal(DATE.to_string())),
+            },
+            Expr::Duration { .. } => Expr::Duration {
+                duration: Box::new(Expr::Literal("P0D".to_string())),
+            },
+            Expr::Property { .. } | Expr::Bool(_) | Expr::Null => self.clone(),
+            Expr::BBox { bbox } => Expr::BBox {
+                bbox: bbox.iter().map(|_| Expr::Float(0.0)).collect(),
+            },
+            Expr::Float(_) => Expr::Float(0.0),
+            Expr::Literal(_) => Expr::Literal(STRING.to_string()),
+            Expr::Array(array) => Expr::Array(array.iter().map(Expr::redact_literals).collect()),
+            Expr::Geometry(_) => Expr::Geometry(Geometry::Wkt("POINT(0 0)".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expr;
+
+    #[test]
+    fn redacted_examples_are_valid() {
+        for entry in std::fs::read_dir("examples/text").unwrap() {
+            let path = entry.unwrap().path();
+            let expr: Expr = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+            if !expr.is_valid() {
+                continue;
+            }
+            let redacted = expr.redact_literals();
+            assert!(redacted.is_valid(), "{}", path.display());
+            assert_eq!(redacted.properties(), expr.properties());
+            assert_eq!(redacted.operators(), expr.operators());
+        }
+    }
+
+    #[test]
+    fn temporal() {
+        let expr: Expr = "t_intersects(datetime, INTERVAL('2020-01-01', '..')) \
+            AND updated > TIMESTAMP('2020-01-01T12:00:00Z')"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.redact_literals().to_text().unwrap(),
+            "(t_intersects(datetime, INTERVAL('1970-01-01','..')) \
+            AND (updated > TIMESTAMP('1970-01-01T00:00:00Z')))"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::Queryables;
+use serde_json::{json, Value};
+
+/// The operators each CQL2 conformance class adds, keyed by the last segment
+/// of the conformance class URI.
+///
+/// Custom functions are allowed by the `functions` class, which isn't listed
+/// here because it doesn't have a fixed set of names.
+const CONFORMANCE_CLASSES: [(&str, &[&str]); 9] = [
+    (
+        "basic-cql2",
+        &[
+            "and", "or", "not", "=", "<>", "<", ">", "<=", ">=", "isnull",
+        ],
+    ),
+    ("advanced-comparison-operators", &["like", "between", "in"]),
+    ("case-insensitive-comparison", &["casei"]),
+    ("accent-insensitive-comparison", &["accenti"]),
+    ("basic-spatial-functions", &["s_intersects"]),
+    (
+        "spatial-functions",
+        &[
+            "s_contains",
+            "s_crosses",
+            "s_disjoint",
+            "s_equals",
+            "s_intersects",
+            "s_overlaps",
+            "s_touches",
+            "s_within",
+        ],
+    ),
+    (
+        "temporal-functions",
+        &[
+            "t_after",
+            "t_before",
+            "t_contains",
+            "t_disjoint",
+            "t_during",
+            "t_equals",
+            "t_finishedby",
+            "t_finishes",
+            "t_intersects",
+            "t_meets",
+            "t_metby",
+            "t_overlappedby",
+            "t_overlaps",
+            "t_startedby",
+            "t_starts",
+        ],
+    ),
+    (
+        "array-functions",
+        &["a_containedby", "a_contains", "a_equals", "a_overlaps"],
+    ),
+    ("arithmetic", &["+", "-", "*", "/", "^", "%", "div"]),
+];
+
+impl Queryables {
+    /// Returns a JSON Schema for the cql2-json filters that a server with
+    /// these queryables and conformance classes accepts.
+    ///
+    /// The schema is the CQL2 JSON Schema with its operators limited to those
+    /// of the conformance classes, e.g.
+    /// `http://www.opengis.net/spec/cql2/1.0/conf/basic-cql2`, and its
+    /// properties limited to the queryables. Custom functions are only
+    /// allowed with the `functions` conformance class. Any version of the
+    /// conformance classes is accepted.
+    ///
+    /// Use [Validator::from_schema](crate::Validator::from_schema) to validate
+    /// against it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Queryables, Validator};
+    ///
+    /// let queryables: Queryables = r#"{"properties": {"eo:cloud_cover": {"type": "number"}}}"#
+    ///     .parse()
+    ///     .unwrap();
+    /// let schema = queryables.filter_schema(["http://www.opengis.net/spec/cql2/1.0/conf/basic-cql2"]);
+    /// let validator = Validator::from_schema(schema).unwrap();
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 10".parse().unwrap();
+    /// assert!(validator.validate_expr(&expr).is_valid());
+    /// let expr: Expr = "eo:cloud_cover BETWEEN 0 AND 10".parse().unwrap();
+    /// assert!(!validator.validate_expr(&expr).is_valid());
+    /// let expr: Expr = "platform = 'landsat-8'".parse().unwrap();
+    /// assert!(!validator.validate_expr(&expr).is_valid());
+    /// ```
+    pub fn filter_schema(&self, conforms_to: impl IntoIterator<Item = impl AsRef<str>>) -> Value {
+        let mut allowed = Vec::new();
+        let mut functions = false;
+        for uri in conforms_to {
+            let uri = uri.as_ref();
+            functions |= uri.ends_with("/conf/functions");
+            for (class, ops) in CONFORMANCE_CLASSES {
+                if uri.ends_with(&format!("/conf/{class}")) {
+                    allowed.extend_from_slice(ops);
+                }
+            }
+        }
+        let mut schema: Value =
+            serde_json::from_str(include_str!("cql2.json")).expect("the cql2 json-schema is JSON");
+        restrict_ops(&mut schema, &allowed);
+        let defs = &mut schema["$defs"];
+        if !functions {
+            defs["functionRef"] = Value::Bool(false);
+        }
+        defs["propertyRef"]["properties"]["property"]["enum"] =
+            json!(self.properties.keys().collect::<Vec<_>>());
+        schema
+    }
+}
+
+/// Limits every `op` enum in a schema to the allowed operators, replacing
+/// subschemas with no allowed operators with `false`.
+///
+/// Operators are compared case-insensitively, and lowercase spellings are
+/// added to the enums, because the parser writes e.g. `t_finishedby` where
+/// the schema has `t_finishedBy`.
+fn restrict_ops(schema: &mut Value, allowed: &[&str]) {
+    let ops = schema
+        .pointer_mut("/properties/op/enum")
+        .and_then(Value::as_array_mut);
+    if let Some(ops) = ops {
+        let mut kept = Vec::new();
+        for op in ops.iter().filter_map(Value::as_str) {
+            if allowed
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(op))
+            {
+                kept.push(Value::from(op));
+                if op.to_lowercase() != op {
+                    kept.push(Value::from(op.to_lowercase()));
+                }
+            }
+        }
+        if kept.is_empty() {
+            *schema = Value::Bool(false);
+            return;
+        }
+        *ops = kept;
+    }
+    match schema {
+        Value::Object(object) => {
+            for value in object.values_mut() {
+                restrict_ops(value, allowed);
+            }
+        }
+        Value::Array(array) => {
+            for value in array {
+                restrict_ops(value, allowed);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expr, Queryables, Validator};
+
+    const CONF: &str = "http://www.opengis.net/spec/cql2/1.0/conf";
+
+    fn is_valid(validator: &Validator, s: &str) -> bool {
+        let expr: Expr = s.parse().unwrap();
+        validator.validate_expr(&expr).is_valid()
+    }
+
+    #[test]
+    fn conformance_classes() {
+        let queryables: Queryables = r#"{"properties": {
+            "datetime": {"type": "string", "format": "date-time"},
+            "platform": {"type": "string"}
+        }}"#
+        .parse()
+        .unwrap();
+        let schema = queryables.filter_schema([
+            format!("{CONF}/basic-cql2"),
+            format!("{CONF}/temporal-functions"),
+            format!("{CONF}/case-insensitive-comparison"),
+        ]);
+        let validator = Validator::from_schema(schema).unwrap();
+        assert!(is_valid(
+            &validator,
+            "t_finishedby(datetime, INTERVAL('2020-01-01', '2021-01-01')) \
+            AND CASEI(platform) = CASEI('Landsat-8')"
+        ));
+        assert!(!is_valid(&validator, "platform LIKE 'landsat%'"));
+        assert!(!is_valid(&validator, "s_intersects(geometry, POINT(0 0))"));
+        assert!(!is_valid(&validator, "my_function(platform)"));
+
+        let schema =
+            queryables.filter_schema([format!("{CONF}/basic-cql2"), format!("{CONF}/functions")]);
+        let validator = Validator::from_schema(schema).unwrap();
+        assert!(is_valid(&validator, "my_function(platform)"));
+        assert!(!is_valid(&validator, "my_function(gsd)"));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::Expr;
+use std::ops::Range;
+
+/// Where the parts of an expression came from in its cql2-text source.
+///
+/// Parts are identified by JSON pointers into the cql2-json representation of
+/// the expression, the same pointers used by validation errors and
+/// [TypeError](crate::TypeError). Created by
+/// [parse_text_with_source_map](crate::parse_text_with_source_map).
+///
+/// # Examples
+///
+/// ```
+/// let (expr, source_map) = cql2::parse_text_with_source_map("foo = 1 AND bar = 'baz'").unwrap();
+/// assert_eq!(source_map.span("/args/1/args/1"), Some(18..23));
+/// assert_eq!(source_map.span("/args/1"), Some(12..23));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    spans: Vec<(String, Range<usize>)>,
+}
+
+impl SourceMap {
+    /// Creates a source map by pairing the leaves of an expression, in order,
+    /// with the spans of the atoms they were parsed from.
+    ///
+    /// If the numbers don't match, the source map is empty.
+    pub(crate) fn new(expr: &Expr, atoms: Vec<Range<usize>>) -> SourceMap {
+        let mut pointers = Vec::new();
+        collect_leaves(expr, String::new(), &mut pointers);
+        if pointers.len() != atoms.len() {
+            return SourceMap::default();
+        }
+        SourceMap {
+            spans: pointers.into_iter().zip(atoms).collect(),
+        }
+    }
+
+    /// Returns the byte range in the source text of the part of the
+    /// expression at a JSON pointer.
+    ///
+    /// Operations span from their first to their last argument. Returns
+    /// `None` if the pointer doesn't match any part of the expression that
+    /// came from the source, e.g. a function with no arguments.
+    pub fn span(&self, pointer: &str) -> Option<Range<usize>> {
+        let prefix = format!("{pointer}/");
+        self.spans
+            .iter()
+            .filter(|(p, _)| p == pointer || p.starts_with(&prefix))
+            .map(|(_, span)| span.clone())
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+    }
+
+    /// Returns the line of source text that contains the part of the
+    /// expression at a JSON pointer, with that part underlined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let source = "foo = 1 AND bar = 'baz'";
+    /// let (_, source_map) = cql2::parse_text_with_source_map(source).unwrap();
+    /// assert_eq!(
+    ///     source_map.highlight(source, "/args/1/args/1").unwrap(),
+    ///     "1 | foo = 1 AND bar = 'baz'\n  |                   ^^^^^"
+    /// );
+    /// ```
+    pub fn highlight(&self, source: &str, pointer: &str) -> Option<String> {
+        let span = self.span(pointer)?;
+        let line_start = source[..span.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map(|i| span.start + i)
+            .unwrap_or(source.len());
+        let line_number = source[..line_start].matches('\n').count() + 1;
+        let number = line_number.to_string();
+        let padding = " ".repeat(number.len());
+        let column = source[line_start..span.start].chars().count();
+        let width = source[span.start..span.end.min(line_end)]
+            .chars()
+            .count()
+            .max(1);
+        Some(format!(
+            "{number} | {}\n{padding} | {}{}",
+            &source[line_start..line_end],
+            " ".repeat(column),
+            "^".repeat(width)
+        ))
+    }
+}
+
+fn collect_leaves(expr: &Expr, pointer: String, pointers: &mut Vec<String>) {
+    match expr {
+        Expr::Operation { args: exprs, .. } => {
+            for (i, expr) in exprs.iter().enumerate() {
+                collect_leaves(expr, format!("{pointer}/args/{i}"), pointers);
+            }
+        }
+        Expr::Interval { interval: exprs } => {
+            for (i, expr) in exprs.iter().enumerate() {
+                collect_leaves(expr, format!("{pointer}/interval/{i}"), pointers);
+            }
+        }
+        Expr::BBox { bbox: exprs } => {
+            for (i, expr) in exprs.iter().enumerate() {
+                collect_leaves(expr, format!("{pointer}/bbox/{i}"), pointers);
+            }
+        }
+        Expr::Array(exprs) => {
+            for (i, expr) in exprs.iter().enumerate() {
+                collect_leaves(expr, format!("{pointer}/{i}"), pointers);
+            }
+        }
+        Expr::Timestamp { timestamp } => {
+            collect_leaves(timestamp, format!("{pointer}/timestamp"), pointers)
+        }
+        Expr::Date { date } => collect_leaves(date, format!("{pointer}/date"), pointers),
+        Expr::Duration { duration } => {
+            collect_leaves(duration, format!("{pointer}/duration"), pointers)
+        }
+        Expr::Float(_)
+        | Expr::Literal(_)
+        | Expr::Bool(_)
+        | Expr::Null
+        | Expr::Property { .. }
+        | Expr::Geometry(_) => pointers.push(pointer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_text_with_source_map;
+
+    #[test]
+    fn negative_and_between() {
+        let (_, source_map) = parse_text_with_source_map("a BETWEEN -1 AND 2").unwrap();
+        assert_eq!(source_map.span("/args/1"), Some(10..12));
+        assert_eq!(source_map.span("/args/2"), Some(17..18));
+    }
+
+    #[test]
+    fn function() {
+        let source = "t_after(datetime, TIMESTAMP('2020-01-01T00:00:00Z'))";
+        let (_, source_map) = parse_text_with_source_map(source).unwrap();
+        assert_eq!(
+            &source[source_map.span("/args/1/timestamp").unwrap()],
+            "'2020-01-01T00:00:00Z'"
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        let source = "foo = 1\nAND bar = 'baz'";
+        let (_, source_map) = parse_text_with_source_map(source).unwrap();
+        assert_eq!(
+            source_map.highlight(source, "/args/1/args/0").unwrap(),
+            "2 | AND bar = 'baz'\n  |     ^^^"
+        );
+    }
+}
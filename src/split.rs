@@ -0,0 +1,150 @@
+use crate::Expr;
+
+/// Where [Expr::split] sends an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    /// The backend can evaluate the operation.
+    Pushdown,
+
+    /// The operation has to be evaluated in-process.
+    Residual,
+}
+
+impl Expr {
+    /// Splits this expression into a part that a backend can evaluate and a
+    /// residual to evaluate in-process.
+    ///
+    /// The top-level AND arguments of the expression are split independently.
+    /// `f` is called with the operator of each operation in an argument,
+    /// other than `and`, `or`, and `not`, and the properties that operation
+    /// uses. An argument is pushed down if `f` returns [Split::Pushdown] for
+    /// all of them, and is otherwise kept in the residual.
+    ///
+    /// Returns `(pushdown, residual)`. Both are valid expressions, either may
+    /// be `true` if it's empty, and `pushdown AND residual` is equivalent to
+    /// this expression, so a backend can pre-filter with `pushdown` and the
+    /// results can then be filtered exactly with `residual`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Split};
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 10 AND s_intersects(geometry, POINT(0 0)) \
+    ///     AND (platform = 'landsat-8' OR view:off_nadir > 5)"
+    ///     .parse()
+    ///     .unwrap();
+    /// let (pushdown, residual) = expr.split(|op, properties| {
+    ///     if ["<", "=", ">"].contains(&op) && !properties.contains(&"view:off_nadir") {
+    ///         Split::Pushdown
+    ///     } else {
+    ///         Split::Residual
+    ///     }
+    /// });
+    /// assert_eq!(pushdown.to_text().unwrap(), "(\"eo:cloud_cover\" < 10)");
+    /// assert_eq!(
+    ///     residual.to_text().unwrap(),
+    ///     "(s_intersects(geometry, POINT(0 0)) AND ((platform = 'landsat-8') OR (\"view:off_nadir\" > 5)))"
+    /// );
+    /// ```
+    pub fn split(&self, mut f: impl FnMut(&str, &[&str]) -> Split) -> (Expr, Expr) {
+        let mut conjuncts = Vec::new();
+        flatten_and(self, &mut conjuncts);
+        let (pushdown, residual): (Vec<_>, Vec<_>) = conjuncts
+            .into_iter()
+            .partition(|conjunct| can_push_down(conjunct, &mut f));
+        (
+            Expr::and_all(pushdown.into_iter().cloned()),
+            Expr::and_all(residual.into_iter().cloned()),
+        )
+    }
+}
+
+fn flatten_and<'a>(expr: &'a Expr, conjuncts: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::Operation { op, args } if op == "and" => {
+            for arg in args {
+                flatten_and(arg, conjuncts);
+            }
+        }
+        expr => conjuncts.push(expr),
+    }
+}
+
+fn can_push_down(expr: &Expr, f: &mut impl FnMut(&str, &[&str]) -> Split) -> bool {
+    let mut stack = vec![expr];
+    while let Some(expr) = stack.pop() {
+        if let Expr::Operation { op, .. } = expr {
+            if !matches!(op.as_ref(), "and" | "or" | "not")
+                && f(op, &expr.properties()) == Split::Residual
+            {
+                return false;
+            }
+        }
+        stack.extend(expr.children().iter().rev());
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Split;
+    use crate::Expr;
+
+    #[test]
+    fn nested_and() {
+        let expr: Expr = r#"{"op": "and", "args": [
+            {"op": "and", "args": [
+                {"op": "=", "args": [{"property": "a"}, 1]},
+                {"op": "like", "args": [{"property": "b"}, "x%"]}
+            ]},
+            {"op": "not", "args": [{"op": "=", "args": [{"property": "c"}, 2]}]}
+        ]}"#
+        .parse()
+        .unwrap();
+        let (pushdown, residual) = expr.split(|op, _| {
+            if op == "=" {
+                Split::Pushdown
+            } else {
+                Split::Residual
+            }
+        });
+        assert_eq!(pushdown.to_text().unwrap(), "((a = 1) AND (NOT (c = 2)))");
+        assert_eq!(residual.to_text().unwrap(), "(b LIKE 'x%')");
+    }
+
+    #[test]
+    fn everything_or_nothing() {
+        let expr: Expr = "a = 1 AND b = 2".parse().unwrap();
+        assert_eq!(
+            expr.split(|_, _| Split::Pushdown),
+            (expr.clone(), Expr::Bool(true))
+        );
+        assert_eq!(
+            expr.split(|_, _| Split::Residual),
+            (Expr::Bool(true), expr.clone())
+        );
+    }
+
+    #[test]
+    fn interval_bounds() {
+        for s in [
+            "t_intersects(datetime, INTERVAL(now() - 'P7D', '..'))",
+            "t_intersects(datetime, INTERVAL(start_datetime, my_fn(end_datetime)))",
+        ] {
+            let expr: Expr = s.parse().unwrap();
+            let mut ops = Vec::new();
+            let (pushdown, residual) = expr.split(|op, _| {
+                ops.push(op.to_string());
+                if op == "t_intersects" {
+                    Split::Pushdown
+                } else {
+                    Split::Residual
+                }
+            });
+            assert_eq!(ops.len(), 2, "{s}");
+            assert_eq!(pushdown, Expr::Bool(true));
+            assert_eq!(residual, expr);
+        }
+    }
+}
@@ -0,0 +1,713 @@
+use crate::{Error, Expr, ToSqlOptions, Type};
+use jiff::{civil::Date, tz::TimeZone, SignedDuration, Span, Timestamp};
+use std::fmt::{Display, Formatter};
+
+/// A range of instants in time, inclusive of both ends.
+///
+/// Timestamps are ranges with the same start and end, and dates cover the
+/// whole (UTC) day. Open (`..`) interval bounds are represented by
+/// [Timestamp::MIN] and [Timestamp::MAX].
+///
+/// # Examples
+///
+/// ```
+/// use cql2::{DateRange, Expr};
+///
+/// let expr: Expr = "INTERVAL('2020-01-01', '2020-12-31')".parse().unwrap();
+/// let date_range = expr.date_range().unwrap().unwrap();
+/// assert_eq!(date_range.start.to_string(), "2020-01-01T00:00:00Z");
+/// assert_eq!(date_range.end.to_string(), "2020-12-31T23:59:59.999999999Z");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    /// The start of the range.
+    pub start: Timestamp,
+
+    /// The end of the range.
+    pub end: Timestamp,
+}
+
+impl DateRange {
+    /// Parses a date or timestamp string into a date range.
+    ///
+    /// Timestamps may use any UTC offset (e.g. `2020-01-01T00:00:00+02:00`),
+    /// and are compared as instants. Note that the CQL2 JSON Schema only
+    /// allows `Z`, so expressions that use other offsets aren't
+    /// [valid](Expr::is_valid).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::DateRange;
+    ///
+    /// let date_range = DateRange::parse("2020-01-01T00:00:00Z").unwrap();
+    /// assert_eq!(date_range.start, date_range.end);
+    /// let date_range = DateRange::parse("2020-01-01").unwrap();
+    /// assert!(date_range.start < date_range.end);
+    /// ```
+    pub fn parse(s: &str) -> Result<DateRange, Error> {
+        if s.contains(['T', 't', ' ']) {
+            let timestamp: Timestamp = s.parse()?;
+            Ok(DateRange {
+                start: timestamp,
+                end: timestamp,
+            })
+        } else {
+            let date: Date = s.parse()?;
+            let start = date.to_zoned(TimeZone::UTC)?.timestamp();
+            let end = date
+                .at(23, 59, 59, 999_999_999)
+                .to_zoned(TimeZone::UTC)?
+                .timestamp();
+            Ok(DateRange { start, end })
+        }
+    }
+
+    /// Returns the range of instants in both this range and the other, or
+    /// `None` if they don't overlap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::DateRange;
+    ///
+    /// let january = DateRange::parse("2020-01-01").unwrap();
+    /// let other = DateRange::parse("2020-01-01T12:00:00Z").unwrap();
+    /// assert_eq!(january.intersection(&other), Some(other));
+    /// ```
+    pub fn intersection(&self, other: &DateRange) -> Option<DateRange> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start <= end {
+            Some(DateRange { start, end })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the smallest range that contains both this range and the
+    /// other, including any gap between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::DateRange;
+    ///
+    /// let a = DateRange::parse("2020-01-01").unwrap();
+    /// let b = DateRange::parse("2020-01-03").unwrap();
+    /// assert_eq!(a.union(&b).to_string(), "2020-01-01T00:00:00Z/2020-01-03T23:59:59.999999999Z");
+    /// ```
+    pub fn union(&self, other: &DateRange) -> DateRange {
+        DateRange {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Returns true if the instant is within this range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::DateRange;
+    ///
+    /// let date_range = DateRange::parse("2020-01-01").unwrap();
+    /// assert!(date_range.contains_instant("2020-01-01T12:00:00Z".parse().unwrap()));
+    /// assert!(!date_range.contains_instant("2020-01-02T00:00:00Z".parse().unwrap()));
+    /// ```
+    pub fn contains_instant(&self, instant: Timestamp) -> bool {
+        self.start <= instant && instant <= self.end
+    }
+
+    /// Returns the length of this range.
+    ///
+    /// Open ranges are measured to [Timestamp::MIN] or [Timestamp::MAX].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::DateRange;
+    ///
+    /// let date_range = DateRange::parse("2020-01-01").unwrap();
+    /// assert_eq!(date_range.duration().as_secs(), 86399);
+    /// ```
+    pub fn duration(&self) -> SignedDuration {
+        self.end.duration_since(self.start)
+    }
+}
+
+impl Display for DateRange {
+    /// Formats the range as an ISO 8601 time interval, using `..` for open
+    /// bounds.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.start == Timestamp::MIN {
+            f.write_str("..")?;
+        } else {
+            write!(f, "{}", self.start)?;
+        }
+        f.write_str("/")?;
+        if self.end == Timestamp::MAX {
+            f.write_str("..")
+        } else {
+            write!(f, "{}", self.end)
+        }
+    }
+}
+
+impl Expr {
+    /// Returns the range of time covered by this expression.
+    ///
+    /// Timestamps, dates, and intervals with literal or open (`..`) bounds
+    /// return their date range. All other expressions, including intervals
+    /// with property bounds, return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "TIMESTAMP('2020-01-01T00:00:00Z')".parse().unwrap();
+    /// let date_range = expr.date_range().unwrap().unwrap();
+    /// assert_eq!(date_range.start, date_range.end);
+    ///
+    /// let expr: Expr = "INTERVAL('2020-01-01', '..')".parse().unwrap();
+    /// let date_range = expr.date_range().unwrap().unwrap();
+    /// assert_eq!(date_range.end, jiff::Timestamp::MAX);
+    ///
+    /// let expr: Expr = "INTERVAL(start_datetime, end_datetime)".parse().unwrap();
+    /// assert!(expr.date_range().unwrap().is_none());
+    /// ```
+    pub fn date_range(&self) -> Result<Option<DateRange>, Error> {
+        match self {
+            Expr::Timestamp { timestamp } => literal_date_range(timestamp),
+            Expr::Date { date } => literal_date_range(date),
+            Expr::Interval { interval } => {
+                if interval.len() != 2 {
+                    return Err(Error::InvalidNumberOfArguments {
+                        name: "interval".to_string(),
+                        actual: interval.len(),
+                        expected: 2,
+                    });
+                }
+                let start = match &interval[0] {
+                    Expr::Literal(s) if s == ".." => Some(Timestamp::MIN),
+                    expr => literal_date_range(expr)?.map(|date_range| date_range.start),
+                };
+                let end = match &interval[1] {
+                    Expr::Literal(s) if s == ".." => Some(Timestamp::MAX),
+                    expr => literal_date_range(expr)?.map(|date_range| date_range.end),
+                };
+                Ok(start.zip(end).map(|(start, end)| DateRange { start, end }))
+            }
+            Expr::Operation { op, args } if (op == "+" || op == "-") && args.len() == 2 => {
+                let (Some(date_range), Some(span)) = (args[0].date_range()?, args[1].duration()?)
+                else {
+                    return Ok(None);
+                };
+                Ok(Some(DateRange {
+                    start: shift(date_range.start, op, span)?,
+                    end: shift(date_range.end, op, span)?,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the span of a `DURATION` expression.
+    ///
+    /// Durations are ISO 8601 duration strings, e.g. `DURATION('P1D')`. All
+    /// other expressions return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "DURATION('PT6H')".parse().unwrap();
+    /// let span = expr.duration().unwrap().unwrap();
+    /// assert_eq!(span.get_hours(), 6);
+    /// ```
+    pub fn duration(&self) -> Result<Option<Span>, Error> {
+        match self {
+            Expr::Duration { duration } => match duration.as_ref() {
+                Expr::Literal(s) => Ok(Some(s.parse()?)),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Replaces `now()` with a timestamp literal for the given instant.
+    ///
+    /// `now() + <duration>` and `now() - <duration>`, where the duration is a
+    /// [DURATION](Expr::duration) or an ISO 8601 duration string (e.g.
+    /// `'P7D'`), are replaced with the shifted timestamp. Durations are applied
+    /// in UTC.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "t_after(datetime, now() - 'P7D')".parse().unwrap();
+    /// let now = "2024-03-08T12:00:00Z".parse().unwrap();
+    /// let expr = expr.resolve_now(now).unwrap();
+    /// assert_eq!(
+    ///     expr.to_text().unwrap(),
+    ///     "t_after(datetime, TIMESTAMP('2024-03-01T12:00:00Z'))"
+    /// );
+    /// ```
+    pub fn resolve_now(&self, now: Timestamp) -> Result<Expr, Error> {
+        match self {
+            expr if expr.is_now() => Ok(timestamp_expr(now)),
+            Expr::Operation { op, args } => {
+                if (op == "+" || op == "-") && args.len() == 2 && args[0].is_now() {
+                    let span = match &args[1] {
+                        Expr::Literal(duration) => Some(duration.parse()?),
+                        expr => expr.duration()?,
+                    };
+                    if let Some(span) = span {
+                        return Ok(timestamp_expr(shift(now, op, span)?));
+                    }
+                }
+                Ok(Expr::Operation {
+                    op: op.clone(),
+                    args: args
+                        .iter()
+                        .map(|expr| expr.resolve_now(now))
+                        .collect::<Result<_, Error>>()?,
+                })
+            }
+            Expr::Interval { interval } => Ok(Expr::Interval {
+                interval: interval
+                    .iter()
+                    .map(|expr| expr.resolve_now(now))
+                    .collect::<Result<_, Error>>()?,
+            }),
+            Expr::Array(array) => Ok(Expr::Array(
+                array
+                    .iter()
+                    .map(|expr| expr.resolve_now(now))
+                    .collect::<Result<_, Error>>()?,
+            )),
+            expr => Ok(expr.clone()),
+        }
+    }
+
+    /// Checks that every date and timestamp literal in this expression is
+    /// strictly valid.
+    ///
+    /// Timestamps must be full RFC 3339 timestamps (e.g.
+    /// `2020-01-01T00:00:00Z`) and dates must be full dates (e.g.
+    /// `2020-01-01`), and both must exist on the calendar. Interval bounds
+    /// must be dates, timestamps, open (`..`), or non-literals. The error
+    /// holds the first literal that failed, and a JSON pointer to it in the
+    /// cql2-json representation of the expression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "t_after(datetime, TIMESTAMP('2020-01-01T00:00:00Z'))".parse().unwrap();
+    /// expr.check_temporal_literals().unwrap();
+    ///
+    /// let expr: Expr = "t_after(datetime, DATE('2020-02-30'))".parse().unwrap();
+    /// assert!(expr.check_temporal_literals().is_err());
+    /// ```
+    pub fn check_temporal_literals(&self) -> Result<(), Error> {
+        check_temporal_literals(self, "")
+    }
+
+    /// Writes this expression as a PostgreSQL `tstzrange` for
+    /// [TemporalFormat::Range](crate::TemporalFormat::Range).
+    ///
+    /// Instants are ranges that include both ends, and dates (including date
+    /// interval bounds) cover the whole day.
+    pub(crate) fn to_sql_range(
+        &self,
+        params: &mut Vec<String>,
+        options: &ToSqlOptions,
+    ) -> Result<String, Error> {
+        match self {
+            Expr::Interval { interval } if interval.len() == 2 => {
+                let (start, lower) = match &interval[0] {
+                    Expr::Literal(s) if s == ".." => ("NULL".to_string(), '('),
+                    bound if bound.is_date_bound() => (bound.to_sql_date(params, options)?, '['),
+                    bound => (
+                        bound.to_sql_typed(params, options, Some(Type::Temporal))?,
+                        '[',
+                    ),
+                };
+                let (end, upper) = match &interval[1] {
+                    Expr::Literal(s) if s == ".." => ("NULL".to_string(), ')'),
+                    bound if bound.is_date_bound() => {
+                        (format!("{} + 1", bound.to_sql_date(params, options)?), ')')
+                    }
+                    bound => (
+                        bound.to_sql_typed(params, options, Some(Type::Temporal))?,
+                        ']',
+                    ),
+                };
+                Ok(format!("TSTZRANGE({start},{end},'{lower}{upper}')"))
+            }
+            Expr::Date { .. } => {
+                let date = self.to_sql_inner(params, options)?;
+                Ok(format!("TSTZRANGE({date},{date} + 1,'[)')"))
+            }
+            expr => {
+                let instant = expr.to_sql_typed(params, options, Some(Type::Temporal))?;
+                Ok(format!("TSTZRANGE({instant},{instant},'[]')"))
+            }
+        }
+    }
+
+    /// Writes a date, or a date string, as a SQL date.
+    fn to_sql_date(
+        &self,
+        params: &mut Vec<String>,
+        options: &ToSqlOptions,
+    ) -> Result<String, Error> {
+        match self {
+            Expr::Date { .. } => self.to_sql_inner(params, options),
+            expr => Ok(format!("{}::date", expr.to_sql_inner(params, options)?)),
+        }
+    }
+
+    fn is_date_bound(&self) -> bool {
+        match self {
+            Expr::Date { .. } => true,
+            Expr::Literal(s) => !s.contains(['T', 't', ' ']),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_now(&self) -> bool {
+        matches!(self, Expr::Operation { op, args } if op.eq_ignore_ascii_case("now") && args.is_empty())
+    }
+}
+
+/// Returns the PostgreSQL range operator with the same semantics as a CQL2
+/// temporal operator, if there is one.
+pub(crate) fn range_operator(op: &str) -> Option<&'static str> {
+    match op {
+        "t_intersects" | "anyinteracts" | "t_disjoint" => Some("&&"),
+        "t_equals" => Some("="),
+        "t_before" => Some("<<"),
+        "t_after" => Some(">>"),
+        _ => None,
+    }
+}
+
+/// Adds (`+`) or subtracts (`-`) a span, in UTC. Open bounds stay open.
+fn shift(timestamp: Timestamp, op: &str, span: Span) -> Result<Timestamp, Error> {
+    if timestamp == Timestamp::MIN || timestamp == Timestamp::MAX {
+        return Ok(timestamp);
+    }
+    let zoned = timestamp.to_zoned(TimeZone::UTC);
+    let zoned = if op == "+" {
+        zoned.checked_add(span)?
+    } else {
+        zoned.checked_sub(span)?
+    };
+    Ok(zoned.timestamp())
+}
+
+fn timestamp_expr(timestamp: Timestamp) -> Expr {
+    Expr::Timestamp {
+        timestamp: Box::new(Expr::Literal(timestamp.to_string())),
+    }
+}
+
+fn check_temporal_literals(expr: &Expr, pointer: &str) -> Result<(), Error> {
+    match expr {
+        Expr::Timestamp { timestamp } => {
+            let pointer = format!("{pointer}/timestamp");
+            match timestamp.as_ref() {
+                Expr::Literal(s) => check_timestamp(s, &pointer),
+                expr => check_temporal_literals(expr, &pointer),
+            }
+        }
+        Expr::Date { date } => {
+            let pointer = format!("{pointer}/date");
+            match date.as_ref() {
+                Expr::Literal(s) => check_date(s, &pointer),
+                expr => check_temporal_literals(expr, &pointer),
+            }
+        }
+        Expr::Interval { interval } => {
+            for (i, bound) in interval.iter().enumerate() {
+                let pointer = format!("{pointer}/interval/{i}");
+                match bound {
+                    Expr::Literal(s) if s == ".." => {}
+                    Expr::Literal(s) if s.contains(['T', 't']) => check_timestamp(s, &pointer)?,
+                    Expr::Literal(s) => check_date(s, &pointer)?,
+                    expr => check_temporal_literals(expr, &pointer)?,
+                }
+            }
+            Ok(())
+        }
+        Expr::Operation { args, .. } => args
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, arg)| check_temporal_literals(arg, &format!("{pointer}/args/{i}"))),
+        Expr::Array(array) => array
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, arg)| check_temporal_literals(arg, &format!("{pointer}/{i}"))),
+        _ => Ok(()),
+    }
+}
+
+fn check_timestamp(s: &str, pointer: &str) -> Result<(), Error> {
+    let b = s.as_bytes();
+    let well_formed = b.len() >= 20
+        && check_date_bytes(&b[..10])
+        && matches!(b[10], b'T' | b't')
+        && is_digits(&b[11..13])
+        && b[13] == b':'
+        && is_digits(&b[14..16])
+        && b[16] == b':'
+        && is_digits(&b[17..19])
+        && is_fraction_and_offset(&b[19..]);
+    if !well_formed {
+        return Err(invalid_temporal_literal(
+            s,
+            pointer,
+            "expected an RFC 3339 timestamp",
+        ));
+    }
+    s.parse::<Timestamp>()
+        .map(|_| ())
+        .map_err(|err| invalid_temporal_literal(s, pointer, err))
+}
+
+/// Checks the end of an RFC 3339 timestamp, after the seconds, e.g. `.123Z`.
+fn is_fraction_and_offset(mut rest: &[u8]) -> bool {
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let len = fraction.iter().take_while(|c| c.is_ascii_digit()).count();
+        if len == 0 {
+            return false;
+        }
+        rest = &fraction[len..];
+    }
+    match rest {
+        [b'Z' | b'z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => is_digits(&[*h1, *h2, *m1, *m2]),
+        _ => false,
+    }
+}
+
+fn check_date(s: &str, pointer: &str) -> Result<(), Error> {
+    if !check_date_bytes(s.as_bytes()) {
+        return Err(invalid_temporal_literal(
+            s,
+            pointer,
+            "expected a YYYY-MM-DD date",
+        ));
+    }
+    s.parse::<Date>()
+        .map(|_| ())
+        .map_err(|err| invalid_temporal_literal(s, pointer, err))
+}
+
+fn check_date_bytes(b: &[u8]) -> bool {
+    b.len() == 10
+        && is_digits(&b[0..4])
+        && b[4] == b'-'
+        && is_digits(&b[5..7])
+        && b[7] == b'-'
+        && is_digits(&b[8..10])
+}
+
+fn is_digits(b: &[u8]) -> bool {
+    b.iter().all(u8::is_ascii_digit)
+}
+
+fn invalid_temporal_literal(literal: &str, pointer: &str, reason: impl ToString) -> Error {
+    Error::InvalidTemporalLiteral {
+        literal: literal.to_string(),
+        pointer: pointer.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn literal_date_range(expr: &Expr) -> Result<Option<DateRange>, Error> {
+    match expr {
+        Expr::Literal(s) if s == ".." => Ok(None),
+        Expr::Literal(s) => DateRange::parse(s).map(Some),
+        _ => expr.date_range(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateRange;
+    use crate::{Expr, TemporalFormat, ToSqlOptions};
+
+    #[test]
+    fn offsets() {
+        let utc = DateRange::parse("2020-01-01T00:00:00Z").unwrap();
+        let offset = DateRange::parse("2020-01-01T02:00:00+02:00").unwrap();
+        assert_eq!(utc, offset);
+        let lowercase = DateRange::parse("2020-01-01t00:00:00z").unwrap();
+        assert_eq!(utc, lowercase);
+    }
+
+    #[test]
+    fn display_open() {
+        let expr: Expr = "INTERVAL('..', '2020-01-01T00:00:00Z')".parse().unwrap();
+        let date_range = expr.date_range().unwrap().unwrap();
+        assert_eq!(date_range.to_string(), "../2020-01-01T00:00:00Z");
+        assert!(date_range.contains_instant(jiff::Timestamp::MIN));
+    }
+
+    #[test]
+    fn dst_boundaries() {
+        // US Eastern falls back at 02:00 EDT on 2021-11-07, so 01:30 happens
+        // twice, an hour apart.
+        let edt = DateRange::parse("2021-11-07T01:30:00-04:00").unwrap();
+        let est = DateRange::parse("2021-11-07T01:30:00-05:00").unwrap();
+        assert_eq!(est.start.as_second() - edt.start.as_second(), 3600);
+        assert!(edt.intersection(&est).is_none());
+
+        // ... and springs forward at 02:00 EST on 2021-03-14, so 01:30 EST and
+        // 03:30 EDT are an hour apart.
+        let est = DateRange::parse("2021-03-14T01:30:00-05:00").unwrap();
+        let edt = DateRange::parse("2021-03-14T03:30:00-04:00").unwrap();
+        assert_eq!(edt.start.as_second() - est.start.as_second(), 3600);
+    }
+
+    #[test]
+    fn resolve_now() {
+        let now = "2024-03-31T12:00:00Z".parse().unwrap();
+        let expr: Expr = "datetime > NOW() + 'P1M' OR datetime IN (now(), now())"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.resolve_now(now).unwrap().to_text().unwrap(),
+            "((datetime > TIMESTAMP('2024-04-30T12:00:00Z')) OR (datetime IN (TIMESTAMP('2024-03-31T12:00:00Z'), TIMESTAMP('2024-03-31T12:00:00Z'))))"
+        );
+        let expr: Expr = "datetime > now() - 'seven days'".parse().unwrap();
+        assert!(expr.resolve_now(now).is_err());
+    }
+
+    #[test]
+    fn duration_arithmetic() {
+        let expr: Expr = "TIMESTAMP('2020-01-31T00:00:00Z') + DURATION('P1M')"
+            .parse()
+            .unwrap();
+        let date_range = expr.date_range().unwrap().unwrap();
+        assert_eq!(date_range.start.to_string(), "2020-02-29T00:00:00Z");
+
+        let expr: Expr = "datetime >= DATE('2020-01-02') - DURATION('PT6H')"
+            .parse()
+            .unwrap();
+        let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+        assert_eq!(date_range.start.to_string(), "2020-01-01T18:00:00Z");
+        assert_eq!(
+            expr.to_sql().unwrap().query,
+            "(\"datetime\" >= ($1::date - $2::interval))"
+        );
+
+        let expr: Expr = r#"{"duration":"P1D"}"#.parse().unwrap();
+        assert_eq!(expr, "DURATION('P1D')".parse().unwrap());
+    }
+
+    #[test]
+    fn strict_literals() {
+        for valid in [
+            "TIMESTAMP('2020-01-01T00:00:00Z')",
+            "TIMESTAMP('2020-01-01T00:00:00.123+02:00')",
+            "DATE('2020-02-29')",
+            "INTERVAL('2020-01-01', '..')",
+            "INTERVAL(start_datetime, '2020-01-01T00:00:00Z')",
+        ] {
+            let expr: Expr = valid.parse().unwrap();
+            expr.check_temporal_literals().unwrap();
+        }
+        for (invalid, literal) in [
+            ("TIMESTAMP('2020')", "2020"),
+            ("TIMESTAMP('2020-01-01 00:00:00Z')", "2020-01-01 00:00:00Z"),
+            ("TIMESTAMP('2020-01-01T00:00:00')", "2020-01-01T00:00:00"),
+            ("TIMESTAMP('2020-01-01T24:00:00Z')", "2020-01-01T24:00:00Z"),
+            ("DATE('2021-02-29')", "2021-02-29"),
+            (
+                "foo = 1 AND t_after(datetime, INTERVAL('2020-01', '..'))",
+                "2020-01",
+            ),
+        ] {
+            let expr: Expr = invalid.parse().unwrap();
+            match expr.check_temporal_literals().unwrap_err() {
+                crate::Error::InvalidTemporalLiteral { literal: l, .. } => assert_eq!(l, literal),
+                err => panic!("unexpected error: {err}"),
+            }
+        }
+    }
+
+    #[test]
+    fn range_sql() {
+        let options = ToSqlOptions {
+            temporal_format: TemporalFormat::Range,
+            ..Default::default()
+        };
+        let expr: Expr = "t_intersects(datetime, INTERVAL('2020-01-01', '2020-01-31T12:00:00Z'))"
+            .parse()
+            .unwrap();
+        let sql = expr.to_sql_with_options(&options).unwrap();
+        assert_eq!(
+            sql.query,
+            "(TSTZRANGE(\"datetime\",\"datetime\",'[]') && TSTZRANGE($1::date,$2,'[]'))"
+        );
+        assert_eq!(sql.params, ["2020-01-01", "2020-01-31T12:00:00Z"]);
+
+        let expr: Expr = "t_disjoint(INTERVAL(start_datetime, end_datetime), DATE('2020-01-01')) AND t_during(datetime, INTERVAL('..', '2020-01-01'))"
+            .parse()
+            .unwrap();
+        let sql = expr.to_sql_with_options(&options).unwrap();
+        assert_eq!(
+            sql.query,
+            "((NOT (TSTZRANGE(\"start_datetime\",\"end_datetime\",'[]') && TSTZRANGE($1::date,$1::date + 1,'[)'))) AND t_during(\"datetime\", TSTZRANGE(NULL,$2)))"
+        );
+    }
+
+    #[test]
+    fn date_and_timestamp() {
+        let date: Expr = "DATE('2020-02-18')".parse().unwrap();
+        let date = date.date_range().unwrap().unwrap();
+        let timestamp: Expr = "TIMESTAMP('2020-02-18T12:00:00Z')".parse().unwrap();
+        let timestamp = timestamp.date_range().unwrap().unwrap();
+        assert_ne!(date, timestamp);
+        assert_eq!(date.intersection(&timestamp), Some(timestamp));
+
+        let expr: Expr = "t_equals(DATE('2020-02-18'), TIMESTAMP('2020-02-18T12:00:00Z'))"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.to_sql().unwrap().query,
+            "t_equals($1::date, $2::timestamptz)"
+        );
+        let options = ToSqlOptions {
+            temporal_format: TemporalFormat::Range,
+            ..Default::default()
+        };
+        assert_eq!(
+            expr.to_sql_with_options(&options).unwrap().query,
+            "(TSTZRANGE($1::date,$1::date + 1,'[)') = TSTZRANGE($2::timestamptz,$2::timestamptz,'[]'))"
+        );
+    }
+
+    #[test]
+    fn offsets_are_preserved() {
+        let expr: Expr = "datetime > TIMESTAMP('2021-11-07T01:30:00-04:00')"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.to_text().unwrap(),
+            "(datetime > TIMESTAMP('2021-11-07T01:30:00-04:00'))"
+        );
+        assert_eq!(expr.to_sql().unwrap().params, ["2021-11-07T01:30:00-04:00"]);
+        let date_range = expr.temporal_extent().unwrap().bounded().unwrap();
+        assert_eq!(date_range.start.to_string(), "2021-11-07T05:30:00Z");
+    }
+}
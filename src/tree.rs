@@ -0,0 +1,166 @@
+use crate::{type_check::infer, Error, Expr, Queryables};
+
+impl Expr {
+    /// Writes this expression as an indented tree, one node per line, with
+    /// the type of each node when it can be inferred.
+    ///
+    /// This makes operator precedence easy to see.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "a = 1 OR b = 2 AND c = 3".parse().unwrap();
+    /// assert_eq!(
+    ///     expr.to_tree().unwrap(),
+    ///     "or: boolean
+    /// ├── =: boolean
+    /// │   ├── a
+    /// │   └── 1: number
+    /// └── and: boolean
+    ///     ├── =: boolean
+    ///     │   ├── b
+    ///     │   └── 2: number
+    ///     └── =: boolean
+    ///         ├── c
+    ///         └── 3: number"
+    /// );
+    /// ```
+    pub fn to_tree(&self) -> Result<String, Error> {
+        self.to_tree_with_queryables(&Queryables::default())
+    }
+
+    /// Writes this expression as an indented tree, using queryables to infer
+    /// the types of properties.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Queryables};
+    ///
+    /// let queryables: Queryables = r#"{"properties": {"a": {"type": "number"}}}"#.parse().unwrap();
+    /// let expr: Expr = "a = 1".parse().unwrap();
+    /// assert_eq!(
+    ///     expr.to_tree_with_queryables(&queryables).unwrap(),
+    ///     "=: boolean\n├── a: number\n└── 1: number"
+    /// );
+    /// ```
+    pub fn to_tree_with_queryables(&self, queryables: &Queryables) -> Result<String, Error> {
+        let mut lines = Vec::new();
+        tree(self, queryables, "", "", &mut lines)?;
+        Ok(lines.join("\n"))
+    }
+
+    /// Writes this expression as a [Graphviz](https://graphviz.org/) DOT
+    /// digraph, with one node per operation or value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Expr;
+    ///
+    /// let expr: Expr = "a = 'b'".parse().unwrap();
+    /// assert_eq!(
+    ///     expr.to_dot().unwrap(),
+    ///     r#"digraph {
+    ///   n0 [label="="];
+    ///   n1 [label="a"];
+    ///   n0 -> n1;
+    ///   n2 [label="'b'"];
+    ///   n0 -> n2;
+    /// }"#
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> Result<String, Error> {
+        let mut lines = vec!["digraph {".to_string()];
+        let _ = dot(self, &mut 0, &mut lines)?;
+        lines.push("}".to_string());
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Returns the label of a node and its children.
+fn node(expr: &Expr) -> Result<(String, Vec<&Expr>), Error> {
+    match expr {
+        Expr::Operation { op, args } => Ok((op.to_string(), args.iter().collect())),
+        Expr::Array(array) => Ok(("array".to_string(), array.iter().collect())),
+        Expr::Property { property } => Ok((property.clone(), Vec::new())),
+        expr => Ok((expr.to_text()?, Vec::new())),
+    }
+}
+
+fn tree(
+    expr: &Expr,
+    queryables: &Queryables,
+    prefix: &str,
+    child_prefix: &str,
+    lines: &mut Vec<String>,
+) -> Result<(), Error> {
+    let (label, children) = node(expr)?;
+    match infer(expr, queryables) {
+        Some(t) => lines.push(format!("{prefix}{label}: {t}")),
+        None => lines.push(format!("{prefix}{label}")),
+    }
+    for (i, child) in children.iter().enumerate() {
+        let (branch, indent) = if i + 1 == children.len() {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        tree(
+            child,
+            queryables,
+            &format!("{child_prefix}{branch}"),
+            &format!("{child_prefix}{indent}"),
+            lines,
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes a node and its children, returning the node's id.
+fn dot(expr: &Expr, next_id: &mut usize, lines: &mut Vec<String>) -> Result<usize, Error> {
+    let id = *next_id;
+    *next_id += 1;
+    let (label, children) = node(expr)?;
+    lines.push(format!(
+        "  n{id} [label=\"{}\"];",
+        label.replace('\\', "\\\\").replace('"', "\\\"")
+    ));
+    for child in children {
+        let child_id = dot(child, next_id, lines)?;
+        lines.push(format!("  n{id} -> n{child_id};"));
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expr;
+
+    #[test]
+    fn tree_array_and_temporal() {
+        let expr: Expr = "a IN ('x', 'y') AND t_after(datetime, DATE('2020-01-01'))"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.to_tree().unwrap(),
+            "and: boolean
+├── in: boolean
+│   ├── a
+│   └── array: array
+│       ├── 'x': string
+│       └── 'y': string
+└── t_after: boolean
+    ├── datetime
+    └── DATE('2020-01-01'): temporal"
+        );
+    }
+
+    #[test]
+    fn dot_escapes_quotes() {
+        let expr: Expr = r#"{"op": "=", "args": [{"property": "a\"b"}, 1]}"#.parse().unwrap();
+        assert!(expr.to_dot().unwrap().contains(r#"n1 [label="a\"b"];"#));
+    }
+}
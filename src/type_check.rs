@@ -0,0 +1,254 @@
+use crate::{
+    expr::{ARITHMETIC_OPS, COMPARISON_OPS},
+    Expr, Queryables, Type,
+};
+use std::fmt::{Display, Formatter};
+
+/// A type error found by [Expr::type_check].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    /// A JSON pointer to the offending operand in the cql2-json representation
+    /// of the expression.
+    pub pointer: String,
+
+    /// The operator that was applied to the operand.
+    pub op: String,
+
+    /// The type the operator expected.
+    pub expected: Type,
+
+    /// The type of the operand.
+    pub actual: Type,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "at '{}': {} expected {}, but got {}",
+            self.pointer, self.op, self.expected, self.actual
+        )
+    }
+}
+
+impl Expr {
+    /// Checks that the operands of each operation in this expression have
+    /// types that are compatible with the operator.
+    ///
+    /// Property types are looked up in the queryables. Operands whose type
+    /// can't be determined (e.g. properties that aren't queryables, or
+    /// functions) are not checked. Returns an empty vector if no type errors
+    /// were found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Queryables, Type};
+    ///
+    /// let queryables: Queryables = r#"{
+    ///     "properties": {
+    ///         "eo:cloud_cover": { "type": "number" },
+    ///         "platform": { "type": "string" }
+    ///     }
+    /// }"#.parse().unwrap();
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 10".parse().unwrap();
+    /// assert!(expr.type_check(&queryables).is_empty());
+    ///
+    /// let expr: Expr = "eo:cloud_cover < 'ten' AND s_intersects(platform, POINT(0 0))".parse().unwrap();
+    /// let errors = expr.type_check(&queryables);
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].pointer, "/args/0/args/1");
+    /// assert_eq!(errors[0].expected, Type::Number);
+    /// assert_eq!(errors[1].actual, Type::String);
+    /// ```
+    pub fn type_check(&self, queryables: &Queryables) -> Vec<TypeError> {
+        let mut errors = Vec::new();
+        check(self, queryables, "", &mut errors);
+        errors
+    }
+}
+
+fn check(expr: &Expr, queryables: &Queryables, pointer: &str, errors: &mut Vec<TypeError>) {
+    let Expr::Operation { op, args } = expr else {
+        return;
+    };
+    for (i, arg) in args.iter().enumerate() {
+        check(arg, queryables, &format!("{pointer}/args/{i}"), errors);
+    }
+    let mut expect = |i: usize, expected: Type, types: &[Type]| {
+        let actual = infer(&args[i], queryables);
+        if let Some(actual) = actual {
+            if !types.contains(&actual) {
+                errors.push(TypeError {
+                    pointer: format!("{pointer}/args/{i}"),
+                    op: op.to_string(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+    };
+    match op.as_ref() {
+        "and" | "or" | "not" => {
+            for i in 0..args.len() {
+                expect(i, Type::Boolean, &[Type::Boolean]);
+            }
+        }
+        "like" | "casei" | "accenti" => {
+            for i in 0..args.len() {
+                expect(i, Type::String, &[Type::String]);
+            }
+        }
+        op if ARITHMETIC_OPS.contains(&op) => {
+            for i in 0..args.len() {
+                expect(i, Type::Number, &[Type::Number]);
+            }
+        }
+        op if op.starts_with("s_") => {
+            for i in 0..args.len() {
+                expect(i, Type::Geometry, &[Type::Geometry]);
+            }
+        }
+        op if op.starts_with("t_") => {
+            for i in 0..args.len() {
+                expect(i, Type::Temporal, &[Type::Temporal]);
+            }
+        }
+        op if op.starts_with("a_") => {
+            for i in 0..args.len() {
+                expect(i, Type::Array, &[Type::Array]);
+            }
+        }
+        op if COMPARISON_OPS.contains(&op) || op == "between" => {
+            if let Some(expected) = args.first().and_then(|arg| infer(arg, queryables)) {
+                for i in 1..args.len() {
+                    expect(i, expected, &compatible(expected));
+                }
+            }
+        }
+        "in" if args.len() == 2 => {
+            if let (Some(expected), Expr::Array(array)) = (infer(&args[0], queryables), &args[1]) {
+                for (i, arg) in array.iter().enumerate() {
+                    if let Some(actual) = infer(arg, queryables) {
+                        if !compatible(expected).contains(&actual) {
+                            errors.push(TypeError {
+                                pointer: format!("{pointer}/args/1/{i}"),
+                                op: op.to_string(),
+                                expected,
+                                actual,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the types that can be compared with the given type.
+///
+/// Strings can hold dates and timestamps, so they are comparable with
+/// temporal values. Null is comparable with everything.
+fn compatible(t: Type) -> Vec<Type> {
+    match t {
+        Type::String | Type::Temporal => vec![Type::String, Type::Temporal, Type::Null],
+        Type::Null => vec![
+            Type::Boolean,
+            Type::Number,
+            Type::String,
+            Type::Temporal,
+            Type::Geometry,
+            Type::Array,
+            Type::Object,
+            Type::Null,
+        ],
+        t => vec![t, Type::Null],
+    }
+}
+
+/// Infers the type of an expression, if possible.
+pub(crate) fn infer(expr: &Expr, queryables: &Queryables) -> Option<Type> {
+    match expr {
+        Expr::Property { property } => queryables.get_type(property),
+        Expr::Float(_) => Some(Type::Number),
+        Expr::Literal(_) => Some(Type::String),
+        Expr::Bool(_) => Some(Type::Boolean),
+        Expr::Null => Some(Type::Null),
+        Expr::Timestamp { .. } | Expr::Date { .. } | Expr::Interval { .. } => Some(Type::Temporal),
+        Expr::Geometry(_) | Expr::BBox { .. } => Some(Type::Geometry),
+        Expr::Array(_) => Some(Type::Array),
+        Expr::Duration { .. } => None,
+        Expr::Operation { op, .. } => match op.as_ref() {
+            "bbox" => Some(Type::Geometry),
+            "casei" | "accenti" => Some(Type::String),
+            "and" | "or" | "not" | "like" | "between" | "in" | "isNull" => Some(Type::Boolean),
+            op if ARITHMETIC_OPS.contains(&op) => Some(Type::Number),
+            op if COMPARISON_OPS.contains(&op) => Some(Type::Boolean),
+            op if op.starts_with("s_") || op.starts_with("t_") || op.starts_with("a_") => {
+                Some(Type::Boolean)
+            }
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expr, Queryables, Type};
+
+    fn queryables() -> Queryables {
+        r#"{
+            "properties": {
+                "eo:cloud_cover": { "type": "number" },
+                "platform": { "type": "string" },
+                "datetime": { "type": "string", "format": "date-time" },
+                "geometry": { "$ref": "https://geojson.org/schema/Geometry.json" },
+                "keywords": { "type": "array" }
+            }
+        }"#
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn ok() {
+        let expr: Expr = "eo:cloud_cover BETWEEN 0 AND 10 AND platform IN ('landsat-8', 'landsat-9') AND t_after(datetime, TIMESTAMP('2020-01-01T00:00:00Z')) AND s_intersects(geometry, POINT(0 0)) AND a_contains(keywords, ('foo')) AND unknown = 'bar'"
+            .parse()
+            .unwrap();
+        assert!(expr.type_check(&queryables()).is_empty());
+    }
+
+    #[test]
+    fn temporal_on_string() {
+        let expr: Expr = "t_after(platform, TIMESTAMP('2020-01-01T00:00:00Z'))"
+            .parse()
+            .unwrap();
+        let errors = expr.type_check(&queryables());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/args/0");
+        assert_eq!(errors[0].expected, Type::Temporal);
+        assert_eq!(errors[0].actual, Type::String);
+    }
+
+    #[test]
+    fn in_mismatch() {
+        let expr: Expr = "eo:cloud_cover IN (1, 'two')".parse().unwrap();
+        let errors = expr.type_check(&queryables());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/args/1/1");
+    }
+
+    #[test]
+    fn arithmetic() {
+        let expr: Expr = "eo:cloud_cover + platform > 10".parse().unwrap();
+        let errors = expr.type_check(&queryables());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/args/0/args/1");
+        assert_eq!(
+            errors[0].to_string(),
+            "at '/args/0/args/1': + expected number, but got string"
+        );
+    }
+}
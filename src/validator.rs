@@ -1,12 +1,44 @@
-use crate::Error;
+use crate::{
+    expr::{ARITHMETIC_OPS, COMPARISON_OPS},
+    Error, Expr, Geometry, DEFAULT_MAX_DEPTH,
+};
 use boon::{Compiler, SchemaIndex, Schemas, ValidationError};
 use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// The definitions in the CQL2 JSON Schema for operations that aren't
+/// boolean expressions, which are validated against them by themselves.
+const OPERAND_DEFS: [&str; 3] = ["arithmeticExpression", "casei", "accenti"];
+
+lazy_static::lazy_static! {
+    static ref DEFAULT_VALIDATOR: Validator =
+        Validator::new().expect("the cql2 json-schema should compile");
+}
+
+/// The problems found by [Validator::validate_expr].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The problems, in the order they were found.
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// A problem with part of an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// A JSON pointer to the problem in the cql2-json representation of the
+    /// expression.
+    pub pointer: String,
+
+    /// A description of the problem.
+    pub message: String,
+}
 
 /// A re-usable json-schema validator for CQL2.
 #[allow(missing_debug_implementations)]
 pub struct Validator {
     schemas: Schemas,
     index: SchemaIndex,
+    operands: Option<[SchemaIndex; 3]>,
 }
 
 impl Validator {
@@ -20,16 +52,61 @@ impl Validator {
     /// let validator = Validator::new().unwrap();
     /// ```
     pub fn new() -> Result<Validator, Error> {
+        let schema_json = serde_json::from_str(include_str!("cql2.json"))?;
+        Validator::from_schema(schema_json)
+    }
+
+    /// Creates a new validator for a different JSON Schema, e.g. one from
+    /// [Queryables::filter_schema](crate::Queryables::filter_schema).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Validator;
+    /// use serde_json::json;
+    ///
+    /// let validator = Validator::from_schema(json!({"type": "boolean"})).unwrap();
+    /// assert!(validator.validate(&json!(true)).is_ok());
+    /// assert!(validator.validate(&json!({"op": "not", "args": [true]})).is_err());
+    /// ```
+    pub fn from_schema(schema: Value) -> Result<Validator, Error> {
         let mut schemas = Schemas::new();
         let mut compiler = Compiler::new();
-        let schema_json = serde_json::from_str(include_str!("cql2.json"))?;
         compiler
-            .add_resource("/tmp/cql2.json", schema_json)
-            .expect("the cql2 json-schema should compile");
+            .add_resource("/tmp/cql2.json", schema)
+            .map_err(|err| Error::InvalidSchema(err.to_string()))?;
         let index = compiler
             .compile("/tmp/cql2.json", &mut schemas)
-            .expect("the cql2 json-schema should compile");
-        Ok(Validator { schemas, index })
+            .map_err(|err| Error::InvalidSchema(err.to_string()))?;
+        let operands = OPERAND_DEFS
+            .map(|def| compiler.compile(&format!("/tmp/cql2.json#/$defs/{def}"), &mut schemas));
+        let operands = match operands {
+            [Ok(arithmetic), Ok(casei), Ok(accenti)] => Some([arithmetic, casei, accenti]),
+            _ => None,
+        };
+        Ok(Validator {
+            schemas,
+            index,
+            operands,
+        })
+    }
+
+    /// Returns a shared validator, which is created the first time it is used.
+    ///
+    /// Compiling the CQL2 JSON Schema is expensive, so prefer this to
+    /// [Validator::new] when validating many expressions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::Validator;
+    /// use serde_json::json;
+    ///
+    /// let validator = Validator::default_ref();
+    /// validator.validate(&json!(true)).unwrap();
+    /// ```
+    pub fn default_ref() -> &'static Validator {
+        &DEFAULT_VALIDATOR
     }
 
     /// Validates a [serde_json::Value].
@@ -60,4 +137,487 @@ impl Validator {
     pub fn validate<'a, 'b>(&'a self, value: &'b Value) -> Result<(), ValidationError<'a, 'b>> {
         self.schemas.validate(value, self.index)
     }
+
+    /// Validates an expression.
+    ///
+    /// In addition to the JSON Schema, this checks things that the schema
+    /// can't express: the number of arguments of known operators, that
+    /// intervals don't end before they start, that bounding boxes have four
+    /// or six coordinates with their minimums below their maximums, and that
+    /// [temporal literals](Expr::check_temporal_literals) are valid.
+    ///
+    /// Only the most specific JSON Schema errors are reported, since a
+    /// failed `oneOf` produces an error for every alternative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Validator};
+    ///
+    /// let validator = Validator::default_ref();
+    /// let expr: Expr = "landsat:scene_id = 'LC82030282019133LGN00'".parse().unwrap();
+    /// assert!(validator.validate_expr(&expr).is_valid());
+    ///
+    /// let expr: Expr = "t_intersects(datetime, INTERVAL('2020-12-31', '2020-01-01'))".parse().unwrap();
+    /// let report = validator.validate_expr(&expr);
+    /// assert_eq!(report.issues[0].pointer, "/args/1");
+    /// assert_eq!(report.issues[0].message, "interval ends before it starts");
+    /// ```
+    pub fn validate_expr(&self, expr: &Expr) -> ValidationReport {
+        let mut issues = Vec::new();
+        if let Err(error) = expr.check_depth(DEFAULT_MAX_DEPTH) {
+            issues.push(ValidationIssue {
+                pointer: String::new(),
+                message: error.to_string(),
+            });
+            return ValidationReport { issues };
+        }
+        issues.extend(self.validate_schema(expr).issues);
+        check(expr, "", &mut issues);
+        if let Err(Error::InvalidTemporalLiteral {
+            pointer, reason, ..
+        }) = expr.check_temporal_literals()
+        {
+            issues.push(ValidationIssue {
+                pointer,
+                message: reason,
+            });
+        }
+        ValidationReport { issues }
+    }
+
+    /// Validates an expression against the JSON Schema only.
+    ///
+    /// Unlike [Validator::validate], this doesn't recurse: each operation is
+    /// validated by itself, with the operations among its arguments replaced
+    /// by stand-ins with the same operator, so an expression that passes
+    /// [Expr::check_depth] can't overflow the stack. Only the most specific
+    /// errors are reported, see [Validator::validate_expr].
+    ///
+    /// Schemas without the CQL2 operand definitions, e.g. `{"type":
+    /// "boolean"}`, are validated against the whole expression at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::{Expr, Validator};
+    ///
+    /// let validator = Validator::default_ref();
+    /// let expr: Expr = "NOT (a = 1 OR b + 1 = 'two')".parse().unwrap();
+    /// assert!(validator.validate_schema(&expr).is_valid());
+    ///
+    /// let expr: Expr = "NOT (a = 1 OR b + 'one' = 2)".parse().unwrap();
+    /// let report = validator.validate_schema(&expr);
+    /// assert_eq!(report.issues[0].pointer, "/args/0/args/1/args/0/args/1");
+    /// ```
+    pub fn validate_schema(&self, expr: &Expr) -> ValidationReport {
+        let mut issues = Vec::new();
+        if let Err(error) = expr.check_depth(DEFAULT_MAX_DEPTH) {
+            issues.push(ValidationIssue {
+                pointer: String::new(),
+                message: error.to_string(),
+            });
+            return ValidationReport { issues };
+        }
+        let Some(operands) = self.operands else {
+            match expr.to_value() {
+                Ok(value) => self.schema_issues(&value, self.index, "", &mut issues),
+                Err(err) => issues.push(ValidationIssue {
+                    pointer: String::new(),
+                    message: err.to_string(),
+                }),
+            }
+            return ValidationReport { issues };
+        };
+        let mut pending = vec![(String::new(), expr)];
+        while let Some((pointer, expr)) = pending.pop() {
+            let index = match expr {
+                _ if pointer.is_empty() => self.index,
+                Expr::Operation { op, .. } if ARITHMETIC_OPS.contains(&op.as_ref()) => operands[0],
+                Expr::Operation { op, .. } if op == "casei" => operands[1],
+                Expr::Operation { op, .. } if op == "accenti" => operands[2],
+                _ => self.index,
+            };
+            let mut operations = Vec::new();
+            let value = match expr {
+                Expr::Operation { op, args } => Expr::Operation {
+                    op: op.clone(),
+                    args: args
+                        .iter()
+                        .enumerate()
+                        .map(|(i, arg)| {
+                            stand_in(arg, format!("{pointer}/args/{i}"), &mut operations)
+                        })
+                        .collect(),
+                },
+                expr => stand_in(expr, pointer.clone(), &mut operations),
+            }
+            .to_value();
+            match value {
+                Ok(value) => self.schema_issues(&value, index, &pointer, &mut issues),
+                Err(err) => issues.push(ValidationIssue {
+                    pointer,
+                    message: err.to_string(),
+                }),
+            }
+            pending.extend(operations.into_iter().rev());
+        }
+        ValidationReport { issues }
+    }
+
+    /// Adds the most specific JSON Schema errors for a value, whose location
+    /// in the whole expression is `pointer`, to `issues`.
+    fn schema_issues(
+        &self,
+        value: &Value,
+        index: SchemaIndex,
+        pointer: &str,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        let Err(error) = self.schemas.validate(value, index) else {
+            return;
+        };
+        let mut leaves = Vec::new();
+        collect_leaf_errors(&error, &mut leaves);
+        let depth = leaves
+            .iter()
+            .map(|leaf| leaf.instance_location.tokens.len())
+            .max()
+            .unwrap_or_default();
+        for leaf in leaves {
+            if leaf.instance_location.tokens.len() == depth {
+                let issue = ValidationIssue {
+                    pointer: format!("{pointer}{}", leaf.instance_location),
+                    message: leaf.kind.to_string(),
+                };
+                if !issues.contains(&issue) {
+                    issues.push(issue);
+                }
+            }
+        }
+    }
+}
+
+impl ValidationReport {
+    /// Returns true if no problems were found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cql2::ValidationReport;
+    ///
+    /// assert!(ValidationReport::default().is_valid());
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at '{}': {}", self.pointer, self.message)
+    }
+}
+
+fn collect_leaf_errors<'a, 's, 'v>(
+    error: &'a ValidationError<'s, 'v>,
+    leaves: &mut Vec<&'a ValidationError<'s, 'v>>,
+) {
+    if error.causes.is_empty() {
+        leaves.push(error);
+    } else {
+        for cause in &error.causes {
+            collect_leaf_errors(cause, leaves);
+        }
+    }
+}
+
+/// Returns a copy of an argument with the operations in it replaced by
+/// stand-ins, adding the operations and their pointers to `operations`.
+fn stand_in<'a>(expr: &'a Expr, pointer: String, operations: &mut Vec<(String, &'a Expr)>) -> Expr {
+    match expr {
+        Expr::Operation { op, .. } => {
+            operations.push((pointer, expr));
+            Expr::Operation {
+                op: op.clone(),
+                args: stand_in_args(op),
+            }
+        }
+        Expr::Interval { interval } => Expr::Interval {
+            interval: interval
+                .iter()
+                .enumerate()
+                .map(|(i, expr)| stand_in(expr, format!("{pointer}/interval/{i}"), operations))
+                .collect(),
+        },
+        Expr::Array(array) => Expr::Array(
+            array
+                .iter()
+                .enumerate()
+                .map(|(i, expr)| stand_in(expr, format!("{pointer}/{i}"), operations))
+                .collect(),
+        ),
+        expr => expr.clone(),
+    }
+}
+
+/// Returns arguments that the JSON Schema accepts for an operator.
+///
+/// They don't use properties, because a schema from
+/// [Queryables::filter_schema](crate::Queryables::filter_schema) only
+/// accepts queryables.
+fn stand_in_args(op: &str) -> Vec<Expr> {
+    let number = || Expr::Float(1.);
+    let string = || Expr::Literal("a".to_string());
+    match op {
+        "and" | "or" => vec![Expr::Bool(true), Expr::Bool(true)],
+        "not" => vec![Expr::Bool(true)],
+        "like" => vec![string(), string()],
+        "in" => vec![number(), Expr::Array(vec![number()])],
+        "isNull" => vec![number()],
+        "casei" | "accenti" => vec![string()],
+        op if op.starts_with("s_") => {
+            let point = || Expr::Geometry(Geometry::Wkt("POINT(0 0)".to_string()));
+            vec![point(), point()]
+        }
+        op if op.starts_with("t_") => {
+            let timestamp = || Expr::Timestamp {
+                timestamp: Box::new(Expr::Literal("2020-01-01T00:00:00Z".to_string())),
+            };
+            vec![timestamp(), timestamp()]
+        }
+        op if op.starts_with("a_") => {
+            vec![Expr::Array(vec![number()]), Expr::Array(vec![number()])]
+        }
+        op => vec![number(); expected_number_of_arguments(op).unwrap_or_default()],
+    }
+}
+
+fn check(expr: &Expr, pointer: &str, issues: &mut Vec<ValidationIssue>) {
+    let mut issue = |message: String| {
+        issues.push(ValidationIssue {
+            pointer: pointer.to_string(),
+            message,
+        })
+    };
+    match expr {
+        Expr::Operation { op, args } => {
+            if op == "bbox" {
+                check_bbox(args, &mut issue);
+            } else if let Some(expected) = expected_number_of_arguments(op) {
+                if args.len() != expected {
+                    issue(format!(
+                        "{op} expects {expected} argument{}, but got {}",
+                        if expected == 1 { "" } else { "s" },
+                        args.len()
+                    ));
+                }
+            } else if (op == "and" || op == "or") && args.len() < 2 {
+                issue(format!(
+                    "{op} expects at least 2 arguments, but got {}",
+                    args.len()
+                ));
+            }
+            for (i, arg) in args.iter().enumerate() {
+                check(arg, &format!("{pointer}/args/{i}"), issues);
+            }
+        }
+        Expr::Interval { interval } => {
+            if interval.len() != 2 {
+                issue(format!(
+                    "interval expects 2 arguments, but got {}",
+                    interval.len()
+                ));
+            } else if let Ok(Some(date_range)) = expr.date_range() {
+                if date_range.start > date_range.end {
+                    issue("interval ends before it starts".to_string());
+                }
+            }
+        }
+        Expr::BBox { bbox } => check_bbox(bbox, &mut issue),
+        Expr::Array(array) => {
+            for (i, expr) in array.iter().enumerate() {
+                check(expr, &format!("{pointer}/{i}"), issues);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_bbox(bbox: &[Expr], issue: &mut impl FnMut(String)) {
+    let values: Option<Vec<f64>> = bbox.iter().map(|expr| expr.as_f64()).collect();
+    match (bbox.len(), values) {
+        (4, Some(v)) if v[1] > v[3] => issue("bbox ymin is greater than ymax".to_string()),
+        (6, Some(v)) if v[1] > v[4] => issue("bbox ymin is greater than ymax".to_string()),
+        (6, Some(v)) if v[2] > v[5] => issue("bbox zmin is greater than zmax".to_string()),
+        (4 | 6, _) => {}
+        (len, _) => issue(format!("bbox expects 4 or 6 coordinates, but got {len}")),
+    }
+}
+
+/// Returns the number of arguments a known operator expects.
+fn expected_number_of_arguments(op: &str) -> Option<usize> {
+    match op {
+        "not" | "isNull" | "casei" | "accenti" => Some(1),
+        "between" => Some(3),
+        "like" | "in" => Some(2),
+        op if COMPARISON_OPS.contains(&op) || ARITHMETIC_OPS.contains(&op) => Some(2),
+        op if op.starts_with("s_") || op.starts_with("t_") || op.starts_with("a_") => Some(2),
+        "anyinteracts" => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stand_in_args, Validator};
+    use crate::{Expr, DEFAULT_MAX_DEPTH};
+    use std::fs;
+
+    fn issues(s: &str) -> Vec<String> {
+        let expr: Expr = s.parse().unwrap();
+        Validator::default_ref()
+            .validate_expr(&expr)
+            .issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        assert!(
+            issues("s_intersects(geometry, BBOX(0, 0, 10, 10)) AND foo BETWEEN 1 AND 2").is_empty()
+        );
+    }
+
+    #[test]
+    fn bbox() {
+        assert_eq!(
+            issues("s_intersects(geometry, BBOX(0, 10, 10, 0))"),
+            ["at '/args/1': bbox ymin is greater than ymax"]
+        );
+    }
+
+    #[test]
+    fn temporal_literal() {
+        assert_eq!(
+            issues("t_after(datetime, DATE('2021-02-29'))"),
+            ["at '/args/1/date': parsed date is not valid: parameter 'day' for `2021-02` is invalid, must be in range `1..=28`"]
+        );
+    }
+
+    #[test]
+    fn number_of_arguments() {
+        let expr: Expr = r#"{"op": "t_after", "args": [{"property": "datetime"}]}"#
+            .parse()
+            .unwrap();
+        let report = Validator::default_ref().validate_expr(&expr);
+        assert!(report.issues.iter().any(|issue| issue.pointer.is_empty()
+            && issue.message == "t_after expects 2 arguments, but got 1"));
+    }
+
+    #[test]
+    fn stand_ins() {
+        let validator = Validator::default_ref();
+        for op in [
+            "and",
+            "or",
+            "not",
+            "=",
+            "<>",
+            "like",
+            "in",
+            "between",
+            "isNull",
+            "s_intersects",
+            "t_finishedBy",
+            "a_contains",
+            "my_function",
+        ] {
+            let expr = Expr::Operation {
+                op: op.to_string().into(),
+                args: stand_in_args(op),
+            };
+            assert!(
+                validator.validate(&expr.to_value().unwrap()).is_ok(),
+                "{op}"
+            );
+        }
+        for op in ["+", "div", "casei", "accenti"] {
+            let operand = Expr::Operation {
+                op: op.to_string().into(),
+                args: stand_in_args(op),
+            };
+            let expr = Expr::Operation {
+                op: "=".into(),
+                args: vec![operand.clone(), operand],
+            };
+            assert!(
+                validator.validate(&expr.to_value().unwrap()).is_ok(),
+                "{op}"
+            );
+        }
+    }
+
+    #[test]
+    fn same_as_whole_schema() {
+        let validator = Validator::default_ref();
+        for entry in fs::read_dir("examples/json").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let expr = crate::parse_file(path).unwrap();
+            assert_eq!(
+                validator.validate_schema(&expr).is_valid(),
+                validator.validate(&expr.to_value().unwrap()).is_ok(),
+                "{expr}"
+            );
+        }
+        for s in [
+            r#"{"op": "not", "args": [{"op": "+", "args": [1, 2]}]}"#,
+            r#"{"op": "=", "args": [{"op": "casei", "args": [1]}, "a"]}"#,
+            r#"{"op": "<", "args": [{"op": "+", "args": [{"op": "casei", "args": ["a"]}, 1]}, 2]}"#,
+            r#"{"op": "in", "args": [{"property": "a"}, [{"op": "and", "args": [true, true]}]]}"#,
+        ] {
+            let expr: Expr = s.parse().unwrap();
+            assert!(!validator.validate_schema(&expr).is_valid(), "{s}");
+            assert!(
+                validator.validate(&expr.to_value().unwrap()).is_err(),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn deep() {
+        let mut not = Expr::Bool(true);
+        let mut function = Expr::Float(1.);
+        for _ in 1..DEFAULT_MAX_DEPTH {
+            not = Expr::Operation {
+                op: "not".into(),
+                args: vec![not],
+            };
+            function = Expr::Operation {
+                op: "f".into(),
+                args: vec![function],
+            };
+        }
+        let validator = Validator::default_ref();
+        assert!(validator.validate_expr(&not).is_valid());
+        assert!(validator.validate_expr(&function).is_valid());
+    }
 }
@@ -9,9 +9,16 @@ fn validate_str(s: &str) -> Expr {
     assert!(expr.is_valid());
     let expr_from_txt: Expr = expr.to_text().unwrap().parse().unwrap();
     assert!(expr_from_txt.is_valid());
+    let expr_from_pretty_txt: Expr = expr.to_text_pretty().unwrap().parse().unwrap();
+    assert_eq!(expr_from_txt, expr_from_pretty_txt);
     let json = expr.to_json().unwrap();
     let expr_from_json: Expr = json.parse().unwrap();
     assert_json_eq!(json!(json), json!(expr_from_json.to_json().unwrap()));
+    #[cfg(feature = "cbor")]
+    {
+        let expr_from_cbor = cql2::parse_cbor(&expr.to_cbor().unwrap()).unwrap();
+        assert_json_eq!(json!(json), json!(expr_from_cbor.to_json().unwrap()));
+    }
     expr
 }
 